@@ -0,0 +1,58 @@
+use std::{
+    fs::{File, create_dir_all, remove_file, rename},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Mode, Session};
+
+/// Everything needed to pick an interrupted session back up, for `--resume`.
+/// This is written after every round, and removed when the session ends.
+#[derive(Serialize,Deserialize)]
+pub struct Checkpoint {
+    pub mode: Mode,
+    pub min_note: u32,
+    pub max_note: u32,
+    pub test_count: u32,
+    /// The seed the notes are being picked with, and how far into its
+    /// stream we've gotten, so the rest of the notes come out the same.
+    pub seed: [u8; 32],
+    pub word_pos: u128,
+    /// The same for the streams the spellings and the jitter are picked
+    /// from.
+    pub spelling_word_pos: u128,
+    pub jitter_word_pos: u128,
+    pub session: Session,
+}
+
+/// Where the checkpoint lives: `$XDG_DATA_HOME/what-note/checkpoint.json`,
+/// next to the history file.
+pub fn default_path() -> Option<PathBuf> {
+    Some(crate::history::default_path()?.with_file_name("checkpoint.json"))
+}
+
+/// Writes a checkpoint. It's written to a temporary file that then replaces
+/// the old one, so being interrupted in the middle doesn't lose the old one.
+pub fn save(path: &Path, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() { create_dir_all(parent)? }
+    let temp_path = path.with_extension("tmp");
+    let mut file = BufWriter::new(File::create(&temp_path)?);
+    serde_json::to_writer(&mut file, checkpoint)?;
+    file.flush()?;
+    drop(file);
+    rename(&temp_path, path)
+}
+
+pub fn load(path: &Path) -> std::io::Result<Checkpoint> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Removes the checkpoint, if there is one.
+pub fn remove(path: &Path) -> std::io::Result<()> {
+    match remove_file(path) {
+        Err(x) if x.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        x => x,
+    }
+}
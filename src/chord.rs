@@ -0,0 +1,157 @@
+use crate::{NOTES_PER_OCTAVE, note_name, spelling::Spelling};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ChordQuality {
+    Major, Minor, Diminished, Augmented, Dominant7, Major7, Minor7,
+}
+
+/// The qualities we test inversions of. (Not augmented, because every
+/// inversion of an augmented triad is just another augmented triad.)
+pub const INVERSION_QUALITIES: &[ChordQuality] = &[ChordQuality::Major,
+                                                   ChordQuality::Minor,
+                                                   ChordQuality::Diminished];
+
+/// The qualities chord quality mode asks about.
+pub const CHORD_QUALITIES: &[ChordQuality] = &[
+    ChordQuality::Major, ChordQuality::Minor, ChordQuality::Diminished,
+    ChordQuality::Augmented, ChordQuality::Dominant7, ChordQuality::Major7,
+    ChordQuality::Minor7,
+];
+
+const INVERSION_NAMES: &[&str] = &["root", "1st", "2nd"];
+
+impl ChordQuality {
+    /// Semitones above the root of each note, in root position.
+    pub fn intervals(self) -> &'static [u32] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            ChordQuality::Major => "major",
+            ChordQuality::Minor => "minor",
+            ChordQuality::Diminished => "diminished",
+            ChordQuality::Augmented => "augmented",
+            ChordQuality::Dominant7 => "dominant 7th",
+            ChordQuality::Major7 => "major 7th",
+            ChordQuality::Minor7 => "minor 7th",
+        }
+    }
+    /// The short name to answer with, like `maj` or `dom7`.
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            ChordQuality::Major => "maj",
+            ChordQuality::Minor => "min",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Dominant7 => "dom7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "min7",
+        }
+    }
+}
+
+/// Parses a chord quality, like `maj`, `minor`, `+`, or `m7`. `M` and `m`
+/// are major and minor, as in chord symbols; otherwise case doesn't matter.
+pub fn parse_chord_quality(name: &str) -> Option<ChordQuality> {
+    let name = name.trim();
+    match name {
+        "M" => return Some(ChordQuality::Major),
+        "m" | "-" => return Some(ChordQuality::Minor),
+        "M7" => return Some(ChordQuality::Major7),
+        "m7" | "-7" => return Some(ChordQuality::Minor7),
+        _ => (),
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "maj" | "major" => Some(ChordQuality::Major),
+        "min" | "minor" => Some(ChordQuality::Minor),
+        "dim" | "diminished" | "o" => Some(ChordQuality::Diminished),
+        "aug" | "augmented" | "+" => Some(ChordQuality::Augmented),
+        "dom7" | "7" | "dominant" | "dominant7" | "dominant 7th"
+            => Some(ChordQuality::Dominant7),
+        "maj7" | "major7" | "major 7th" => Some(ChordQuality::Major7),
+        "min7" | "minor7" | "minor 7th" => Some(ChordQuality::Minor7),
+        _ => None,
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Chord {
+    pub root: u32,
+    pub quality: ChordQuality,
+    /// 0 for root position, 1 for first inversion, etc.
+    pub inversion: u32,
+}
+
+impl Chord {
+    /// The notes of the chord, lowest first. Each inversion moves the lowest
+    /// note up an octave.
+    pub fn notes(&self) -> Vec<u32> {
+        let mut notes: Vec<u32> = self.quality.intervals().iter()
+            .map(|interval| self.root + interval).collect();
+        for _ in 0 .. self.inversion {
+            let lowest = notes.remove(0);
+            notes.push(lowest + NOTES_PER_OCTAVE);
+        }
+        notes
+    }
+    /// How far the highest note can be above the root, for any inversion of
+    /// a chord of this quality.
+    pub fn max_span(quality: ChordQuality) -> u32 {
+        let intervals = quality.intervals();
+        intervals[intervals.len() - 2] + NOTES_PER_OCTAVE
+    }
+    pub fn name(&self) -> String {
+        format!("{} {}", note_name(self.root), self.quality.name())
+    }
+}
+
+pub fn inversion_name(inversion: u32) -> &'static str {
+    INVERSION_NAMES[inversion as usize]
+}
+
+/// Parses an inversion, like `root`, `1st`, or `second`.
+pub fn parse_inversion(name: &str) -> Option<u32> {
+    match name.to_ascii_lowercase().as_str() {
+        "root" | "r" | "0" => Some(0),
+        "1st" | "first" | "1" => Some(1),
+        "2nd" | "second" | "2" => Some(2),
+        _ => None,
+    }
+}
+
+/// How many letters and semitones each note of a chord is above its root.
+type ChordTones = &'static [(usize, u32)];
+
+/// Chord symbol suffixes we know, and the notes of each chord.
+const CHORD_SYMBOLS: &[(&[&str], ChordTones)] = &[
+    (&["", "maj", "M"], &[(0, 0), (2, 4), (4, 7)]),
+    (&["m", "min", "-"], &[(0, 0), (2, 3), (4, 7)]),
+    (&["dim", "o"], &[(0, 0), (2, 3), (4, 6)]),
+    (&["aug", "+"], &[(0, 0), (2, 4), (4, 8)]),
+    (&["7", "dom7"], &[(0, 0), (2, 4), (4, 7), (6, 10)]),
+    (&["maj7", "M7"], &[(0, 0), (2, 4), (4, 7), (6, 11)]),
+    (&["m7", "min7", "-7"], &[(0, 0), (2, 3), (4, 7), (6, 10)]),
+    (&["m7b5"], &[(0, 0), (2, 3), (4, 6), (6, 10)]),
+    (&["dim7", "o7"], &[(0, 0), (2, 3), (4, 6), (6, 9)]),
+    (&["sus2"], &[(0, 0), (1, 2), (4, 7)]),
+    (&["sus4", "sus"], &[(0, 0), (3, 5), (4, 7)]),
+];
+
+/// Parses a chord symbol, like `C7`, `F#m`, or `Bbmaj7`, and spells out its
+/// notes from the root up.
+pub fn parse_chord_symbol(name: &str) -> Option<Vec<Spelling>> {
+    let (root, suffix) = Spelling::parse_prefix(name.trim())?;
+    let (_, notes) = CHORD_SYMBOLS.iter()
+        .find(|(suffixes, _)| suffixes.contains(&suffix))?;
+    Some(notes.iter()
+         .map(|&(letters, semitones)| root.above(letters, semitones))
+         .collect())
+}
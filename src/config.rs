@@ -0,0 +1,137 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+use clap::{ArgMatches, Command as ClapCommand, ValueSource};
+
+/// One setting from the config file: an option's name, and what it's set
+/// to.
+struct Entry {
+    line: usize,
+    key: String,
+    value: Value,
+}
+
+enum Value { Text(String), Bool(bool) }
+
+/// Where the config file lives if `--config` isn't given:
+/// `$XDG_CONFIG_HOME/what-note/config.toml`, falling back to
+/// `~/.config/what-note/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("what-note").join("config.toml"))
+}
+
+/// Reads a config file: `key = value` lines, where each key names an option
+/// the way `--explain-config` does, without the dashes (`low`, `octaves`),
+/// or, for options with only a short form, the way `--help` describes them
+/// (`attempt-limit` for `-a`), and each value is a string, a number, or
+/// `true` or `false`. That's all of TOML we understand. Blank lines and
+/// comments are ignored.
+fn read(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|x| format!("Couldn't read {:?}: {}", path, x))?;
+    let mut entries = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let bad = |what: &str| format!("Line {} of {:?} {}.", index + 1, path,
+                                       what);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| bad("should look like `key = value`"))?;
+        let key = key.trim().trim_matches('"').replace('_', "-");
+        let value = value.trim();
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            let end = quoted.find('"')
+                .ok_or_else(|| bad("has a string with no end"))?;
+            let rest = quoted[end + 1 ..].trim();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(bad("has something after its value"))
+            }
+            Value::Text(quoted[.. end].to_string())
+        }
+        else {
+            let value = value.split(" #").next().unwrap().trim();
+            match value {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ if value.parse::<f64>().is_ok() => {
+                    Value::Text(value.to_string())
+                },
+                _ => return Err(bad("has a value that isn't a string, a \
+                                     number, true, or false")),
+            }
+        };
+        entries.push(Entry { line: index + 1, key, value });
+    }
+    Ok(entries)
+}
+
+/// Whether `option` can be given with no value, like `--no-repeat`. This
+/// version of clap won't say whether an option has a default for a missing
+/// value, so we try it and see.
+fn takes_no_value(command: &ClapCommand, option: &OsString) -> bool {
+    command.clone()
+        .try_get_matches_from([OsString::from(command.get_name()),
+                               option.clone()])
+        .is_ok()
+}
+
+/// Reads the config file at `path`, and works out the arguments it adds to
+/// the command line: only those for options that weren't already given on
+/// the command line or in the environment, so that those win. Also returns
+/// the IDs of the options the config file set.
+pub fn args(command: &ClapCommand, matches: &ArgMatches, path: &Path)
+            -> Result<(Vec<OsString>, Vec<String>), String> {
+    let mut args = vec![];
+    let mut ids = vec![];
+    for entry in read(path)? {
+        let arg = command.get_arguments()
+            .find(|arg| arg.get_long() == Some(entry.key.as_str())
+                  || arg.get_id() == entry.key)
+            .ok_or_else(|| format!("Line {} of {:?}: there's no {} option.",
+                                   entry.line, path, entry.key))?;
+        let id = arg.get_id();
+        let option = match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => OsString::from(format!("--{}", long)),
+            (None, Some(short)) => OsString::from(format!("-{}", short)),
+            (None, None) => continue,
+        };
+        if matches!(matches.value_source(id),
+                    Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+            || matches.occurrences_of(id) > 0 {
+            continue
+        }
+        match (entry.value, arg.is_takes_value_set()) {
+            (Value::Bool(false), false) => continue,
+            (Value::Bool(true), false) => args.push(option),
+            (Value::Text(count), false) => {
+                // A flag that can be given more than once, like --verbose.
+                let count: usize = count.parse().ok()
+                    .filter(|_| arg.is_multiple_occurrences_set())
+                    .ok_or_else(|| format!("Line {} of {:?}: {} should be \
+                                            true or false.", entry.line, path,
+                                           entry.key))?;
+                args.extend(std::iter::repeat_n(option, count));
+            },
+            // An option whose value can be left off, like `--no-repeat`.
+            (Value::Bool(false), true) if takes_no_value(command, &option)
+                => continue,
+            (Value::Bool(true), true) if takes_no_value(command, &option)
+                => args.push(option),
+            (Value::Text(value), true) => {
+                args.push(option);
+                args.push(value.into());
+            },
+            (Value::Bool(value), true) => {
+                args.push(option);
+                args.push(value.to_string().into());
+            },
+        }
+        ids.push(id.to_string());
+    }
+    Ok((args, ids))
+}
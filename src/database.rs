@@ -0,0 +1,99 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    NOTE_NAMES, NOTES_PER_OCTAVE, OCTAVE_OFFSET,
+    history::{Outcome, SessionRecord},
+};
+
+/// The schema version that `migrate` brings a database up to.
+const SCHEMA_VERSION: u32 = 1;
+
+/// SQL that creates whatever's missing from the schema, and marks the
+/// database as being at `SCHEMA_VERSION`. Everything in here has to be safe
+/// to run again on a database that's already up to date.
+fn migrate() -> String {
+    format!("CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    time INTEGER NOT NULL,
+    mode TEXT NOT NULL,
+    min_note TEXT NOT NULL,
+    max_note TEXT NOT NULL,
+    rounds INTEGER NOT NULL,
+    perfect INTEGER NOT NULL,
+    wrong_octave INTEGER NOT NULL,
+    hedged INTEGER NOT NULL,
+    score INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    session INTEGER NOT NULL REFERENCES sessions (id),
+    number INTEGER NOT NULL,
+    note TEXT NOT NULL,
+    notes TEXT NOT NULL,
+    outcome TEXT NOT NULL
+);
+PRAGMA user_version = {};
+", SCHEMA_VERSION)
+}
+
+/// Notes are always named the same way in the database, like `F#4`,
+/// whatever `--naming` says.
+fn note_name(note: u32) -> String {
+    format!("{}{}", NOTE_NAMES[(note % NOTES_PER_OCTAVE) as usize],
+            note / NOTES_PER_OCTAVE - OCTAVE_OFFSET)
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Perfect => "perfect",
+        Outcome::WrongOctave => "wrong-octave",
+        Outcome::Hedged => "hedged",
+        Outcome::Missed => "missed",
+    }
+}
+
+/// Records a session (and, with `rounds`, each of its rounds) in the SQLite
+/// database at `path`, for `--db`, creating the schema if it isn't there.
+/// It all goes in one transaction, so a session is recorded whole or not at
+/// all. This runs the `sqlite3` command-line tool.
+pub fn record(path: &Path, record: &SessionRecord, rounds: bool)
+              -> Result<(), String> {
+    let mode = serde_json::to_value(record.mode).ok()
+        .and_then(|x| x.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut sql = migrate();
+    sql += "BEGIN;\n";
+    sql += &format!("INSERT INTO sessions (time, mode, min_note, max_note, \
+                     rounds, perfect, wrong_octave, hedged, score) VALUES \
+                     ({}, '{}', '{}', '{}', {}, {}, {}, {}, {});\n",
+                    record.time, mode, note_name(record.min_note),
+                    note_name(record.max_note), record.rounds,
+                    record.perfect, record.wrong_octave, record.hedged,
+                    record.score);
+    if rounds {
+        sql += "CREATE TEMP TABLE this_session AS \
+                SELECT last_insert_rowid() AS id;\n";
+        for (index, round) in record.round_records.iter().enumerate() {
+            let names: Vec<String> = round.notes.iter()
+                .map(|&note| note_name(note)).collect();
+            sql += &format!("INSERT INTO rounds (session, number, note, notes, \
+                             outcome) SELECT id, {}, '{}', '{}', '{}' FROM \
+                             this_session;\n", index + 1,
+                            names.first().map_or("", String::as_str),
+                            names.join(" "), outcome_name(round.outcome));
+        }
+    }
+    sql += "COMMIT;\n";
+    let mut child = Command::new("sqlite3").arg("-bail").arg(path)
+        .stdin(Stdio::piped()).spawn()
+        .map_err(|x| format!("Couldn't run `sqlite3`: {}", x))?;
+    child.stdin.take().unwrap().write_all(sql.as_bytes())
+        .map_err(|x| format!("Couldn't talk to `sqlite3`: {}", x))?;
+    let status = child.wait()
+        .map_err(|x| format!("Couldn't run `sqlite3`: {}", x))?;
+    if status.success() { Ok(()) }
+    else { Err(format!("`sqlite3` failed ({}).", status)) }
+}
@@ -0,0 +1,56 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use crate::history::Outcome;
+
+/// The output side of a simple line protocol for frontends (`--output-fd`).
+/// Each line is a word saying what happened, then its details, separated by
+/// spaces:
+///
+/// - `round <N> <TOTAL>`: round N (counting from 1) of TOTAL is starting.
+///   TOTAL is left out in a timed session (`--duration-minutes`), and for
+///   a sudden-death round (`--sudden-death`).
+/// - `result <N> <OUTCOME> <ANSWER>`: round N is over. OUTCOME is one of
+///   `perfect`, `wrong-octave`, `hedged`, or `missed`. ANSWER is the rest of
+///   the line, and is left out in blind mode.
+/// - `done <SCORE>`: the session is over, with a score of SCORE percent.
+///
+/// Guesses go in the other way (see `--input-fd`), one per line, just like
+/// they'd be typed.
+pub struct Events {
+    file: Option<File>,
+}
+
+impl Events {
+    /// Opens the channel. If `path` is a named pipe, this waits for the
+    /// frontend to open the other end.
+    pub fn open(path: Option<&Path>) -> std::io::Result<Events> {
+        let file = match path {
+            Some(path) => Some(OpenOptions::new().write(true).create(true)
+                               .truncate(true).open(path)?),
+            None => None,
+        };
+        Ok(Events { file })
+    }
+    /// Sends one line. If that fails, the channel is closed, so the error is
+    /// only reported once.
+    pub fn send(&mut self, line: &str) {
+        let Some(file) = self.file.as_mut() else { return };
+        if let Err(x) = file.write_all(format!("{}\n", line).as_bytes()) {
+            eprintln!("Couldn't send to the frontend: {}", x);
+            self.file = None;
+        }
+    }
+}
+
+pub fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Perfect => "perfect",
+        Outcome::WrongOctave => "wrong-octave",
+        Outcome::Hedged => "hedged",
+        Outcome::Missed => "missed",
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Decorative characters. Everything that isn't plain ASCII goes through
+/// here, so that `--ascii` can swap it all out at once.
+pub struct Glyphs {
+    pub bar_filled: char,
+    pub bar_empty: char,
+    pub black_key: char,
+    /// Fills in the key being pointed out on a keyboard.
+    pub marked_key: char,
+    /// Sparkline bars, shortest first.
+    pub sparks: &'static [char],
+    pub staff_line: char,
+    pub note_head: char,
+    /// A note head on a staff that's being pointed out.
+    pub marked_note: char,
+    /// Marks a diminished chord's roman numeral.
+    pub diminished: char,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    bar_filled: '█',
+    bar_empty: '░',
+    black_key: '█',
+    marked_key: '▒',
+    sparks: &['▁','▂','▃','▄','▅','▆','▇','█'],
+    staff_line: '─',
+    note_head: '●',
+    marked_note: '◆',
+    diminished: '°',
+};
+
+const ASCII: Glyphs = Glyphs {
+    bar_filled: '#',
+    bar_empty: '.',
+    black_key: '#',
+    marked_key: '*',
+    sparks: &['_','.','-','=','#'],
+    staff_line: '-',
+    note_head: 'o',
+    marked_note: '@',
+    diminished: 'o',
+};
+
+static USE_ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(ascii: bool) {
+    USE_ASCII.store(ascii, Ordering::Relaxed);
+}
+
+pub fn glyphs() -> &'static Glyphs {
+    if USE_ASCII.load(Ordering::Relaxed) { &ASCII } else { &UNICODE }
+}
@@ -0,0 +1,365 @@
+use std::{
+    fs::{File, OpenOptions, create_dir_all},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Mode, NOTES_PER_OCTAVE, full_note_name, glyphs::glyphs,
+    naming::note_names, report::{ConfusionMatrix, print_confusion},
+};
+
+/// How many sessions at each end of the history to compare, when working out
+/// whether you're getting better.
+const TREND_SESSIONS: usize = 5;
+/// How many of the hardest pitch classes to list.
+const HARDEST_COUNT: usize = 3;
+/// How many of the likeliest mix-ups to spell out, with `--stats`.
+const MIXUP_COUNT: usize = 5;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome { Perfect, WrongOctave, Hedged, Missed }
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+pub struct RoundRecord {
+    pub notes: Vec<u32>,
+    pub outcome: Outcome,
+    /// What you said it sounded like, with `--reflect`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reflection: Option<String>,
+    /// In note mode, the pitch class of each guess, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guesses: Vec<u32>,
+}
+
+/// One line of the history file.
+#[derive(Debug,Serialize,Deserialize)]
+pub struct SessionRecord {
+    /// Seconds since the Unix epoch, when the session ended.
+    pub time: u64,
+    pub mode: Mode,
+    pub min_note: u32,
+    pub max_note: u32,
+    pub rounds: u32,
+    pub perfect: u32,
+    pub wrong_octave: u32,
+    pub hedged: u32,
+    pub score: u32,
+    #[serde(default)]
+    pub round_records: Vec<RoundRecord>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs()).unwrap_or(0)
+}
+
+/// Where the history file lives if `--history` isn't given:
+/// `$XDG_DATA_HOME/what-note/history.jsonl`, falling back to
+/// `~/.local/share/what-note/history.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(data_home.join("what-note").join("history.jsonl"))
+}
+
+/// Appends a record to the history file, creating it if needed.
+pub fn append(path: &Path, record: &SessionRecord) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() { create_dir_all(parent)? }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let line = serde_json::to_string(record)?;
+    writeln!(file, "{}", line)
+}
+
+/// Reads every record in the history file. A missing file is an empty
+/// history. Lines that can't be understood are skipped, with a warning.
+pub fn load(path: &Path) -> std::io::Result<Vec<SessionRecord>> {
+    let file = match File::open(path) {
+        Ok(x) => x,
+        Err(x) if x.kind() == std::io::ErrorKind::NotFound
+            => return Ok(vec![]),
+        Err(x) => return Err(x),
+    };
+    let mut records = vec![];
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() { continue }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(x) => eprintln!("Skipping line {} of {:?}: {}", index + 1,
+                                path, x),
+        }
+    }
+    Ok(records)
+}
+
+#[derive(Debug,PartialEq)]
+pub struct LifetimeStats {
+    pub sessions: usize,
+    pub notes_tested: u32,
+    /// Percent of rounds answered perfectly.
+    pub accuracy: f32,
+    pub best_score: u32,
+    /// Average score of the latest few sessions minus that of the earliest
+    /// few. `None` if there isn't enough history to say.
+    pub trend: Option<f32>,
+    /// Pitch classes with the most misses, as (pitch class, rounds, misses),
+    /// worst first.
+    pub hardest: Vec<(u32, u32, u32)>,
+}
+
+pub fn lifetime_stats(records: &[SessionRecord]) -> LifetimeStats {
+    let notes_tested = records.iter().map(|x| x.rounds).sum();
+    let perfect: u32 = records.iter().map(|x| x.perfect).sum();
+    let accuracy = if notes_tested == 0 { 0.0 }
+    else { perfect as f32 * 100.0 / notes_tested as f32 };
+    let best_score = records.iter().map(|x| x.score).max().unwrap_or(0);
+    let average = |records: &[SessionRecord]| {
+        records.iter().map(|x| x.score as f32).sum::<f32>()
+            / records.len() as f32
+    };
+    let trend = if records.len() >= TREND_SESSIONS * 2 {
+        Some(average(&records[records.len() - TREND_SESSIONS ..])
+             - average(&records[.. TREND_SESSIONS]))
+    } else { None };
+    let mut by_pitch_class = [(0u32, 0u32); NOTES_PER_OCTAVE as usize];
+    for round in records.iter().flat_map(|x| x.round_records.iter()) {
+        if round.notes.len() != 1 { continue }
+        let entry = &mut by_pitch_class[(round.notes[0] % NOTES_PER_OCTAVE)
+                                        as usize];
+        entry.0 += 1;
+        if round.outcome != Outcome::Perfect { entry.1 += 1 }
+    }
+    let mut hardest: Vec<(u32, u32, u32)> = by_pitch_class.iter().enumerate()
+        .filter(|(_, (_, misses))| *misses > 0)
+        .map(|(pitch_class, &(rounds, misses))| (pitch_class as u32, rounds,
+                                                 misses))
+        .collect();
+    hardest.sort_by(|a, b| {
+        let a_rate = a.2 as f32 / a.1 as f32;
+        let b_rate = b.2 as f32 / b.1 as f32;
+        b_rate.partial_cmp(&a_rate).unwrap().then(b.2.cmp(&a.2))
+    });
+    hardest.truncate(HARDEST_COUNT);
+    LifetimeStats { sessions: records.len(), notes_tested, accuracy,
+                    best_score, trend, hardest }
+}
+
+pub fn print_lifetime_stats(out: &mut dyn Write, stats: &LifetimeStats)
+                            -> std::io::Result<()> {
+    if stats.sessions == 0 {
+        return writeln!(out, "No sessions recorded yet.")
+    }
+    writeln!(out, "Sessions: {}", stats.sessions)?;
+    writeln!(out, "Notes tested: {}", stats.notes_tested)?;
+    writeln!(out, "Overall accuracy: {:.1}%", stats.accuracy)?;
+    writeln!(out, "Best score: {}%", stats.best_score)?;
+    match stats.trend {
+        Some(trend) => writeln!(out, "Trend: {:+.1}% (your last {} sessions \
+                                      vs. your first {})", trend,
+                                TREND_SESSIONS, TREND_SESSIONS)?,
+        None => writeln!(out, "Trend: not enough sessions yet")?,
+    }
+    if !stats.hardest.is_empty() {
+        writeln!(out, "Hardest notes:")?;
+        for &(pitch_class, rounds, misses) in stats.hardest.iter() {
+            writeln!(out, "{:>4}: missed {} of {}",
+                     note_names()[pitch_class as usize], misses, rounds)?;
+        }
+    }
+    Ok(())
+}
+
+/// Draws one character per score, taller for higher scores.
+pub fn sparkline(scores: &[u32]) -> String {
+    let sparks = glyphs().sparks;
+    scores.iter().map(|&score| {
+        let level = (score.min(100) as usize * (sparks.len() - 1) + 50) / 100;
+        sparks[level]
+    }).collect()
+}
+
+/// Prints a sparkline of the scores of the last `sessions` sessions.
+pub fn print_trend(out: &mut dyn Write, records: &[SessionRecord],
+                   sessions: usize) -> std::io::Result<()> {
+    let records = &records[records.len().saturating_sub(sessions) ..];
+    if records.is_empty() {
+        return writeln!(out, "No sessions recorded yet.")
+    }
+    let scores: Vec<u32> = records.iter().map(|x| x.score).collect();
+    writeln!(out, "Last {} scores: {} (from {}% to {}%)", scores.len(),
+             sparkline(&scores), scores[0], scores[scores.len() - 1])
+}
+
+/// How `record` stacks up against the earlier sessions in `past` with the
+/// same mode and range.
+pub fn comparison(past: &[SessionRecord], record: &SessionRecord) -> String {
+    let scores: Vec<u32> = past.iter()
+        .filter(|x| x.mode == record.mode && x.min_note == record.min_note
+                && x.max_note == record.max_note)
+        .map(|x| x.score).collect();
+    let Some(&best) = scores.iter().max()
+    else { return "This is your first session with these settings.".into() };
+    let average = scores.iter().sum::<u32>() as f32 / scores.len() as f32;
+    let difference = record.score as f32 - average;
+    let versus_average = if difference.abs() < 0.5 {
+        "right at your average".to_string()
+    }
+    else {
+        format!("{:.0}% {} your average", difference.abs(),
+                if difference > 0.0 { "above" } else { "below" })
+    };
+    if record.score > best {
+        format!("New best! That's {}, and beats your old best of {}%.",
+                versus_average, best)
+    }
+    else if record.score == best {
+        format!("That ties your best, and is {}.", versus_average)
+    }
+    else {
+        format!("That's {}, over {} {} with these settings. Your best is \
+                 {}%.", versus_average, scores.len(),
+                if scores.len() == 1 { "session" } else { "sessions" }, best)
+    }
+}
+
+/// Every note in the rounds missed in the latest session, lowest first.
+pub fn last_missed(records: &[SessionRecord]) -> Vec<u32> {
+    let Some(last) = records.last() else { return vec![] };
+    let mut notes: Vec<u32> = last.round_records.iter()
+        .filter(|x| x.outcome == Outcome::Missed)
+        .flat_map(|x| x.notes.iter().copied()).collect();
+    notes.sort_unstable();
+    notes.dedup();
+    notes
+}
+
+/// Every note-mode guess in the history, as a confusion matrix.
+pub fn confusion(records: &[SessionRecord]) -> ConfusionMatrix {
+    let mut matrix = ConfusionMatrix::default();
+    for round in records.iter().flat_map(|x| x.round_records.iter()) {
+        let [note] = round.notes[..] else { continue };
+        let played = (note % NOTES_PER_OCTAVE) as usize;
+        for &guess in round.guesses.iter() {
+            matrix[played][guess as usize] += 1;
+        }
+    }
+    matrix
+}
+
+/// Prints how often each note has been gotten right, the confusion matrix
+/// for every guess in the history, and the worst mix-ups, for `--stats`.
+pub fn print_note_stats(out: &mut dyn Write, records: &[SessionRecord])
+                        -> std::io::Result<()> {
+    let mut by_note: Vec<(u32, u32, u32)> = vec![];
+    for round in records.iter().flat_map(|x| x.round_records.iter()) {
+        let [note] = round.notes[..] else { continue };
+        let index = match by_note.iter().position(|x| x.0 == note) {
+            Some(index) => index,
+            None => { by_note.push((note, 0, 0)); by_note.len() - 1 },
+        };
+        by_note[index].1 += 1;
+        if round.outcome == Outcome::Perfect { by_note[index].2 += 1 }
+    }
+    if by_note.is_empty() {
+        return writeln!(out, "No single notes recorded yet.")
+    }
+    by_note.sort_unstable();
+    writeln!(out, "Note Rounds Correct Accuracy")?;
+    for (note, rounds, perfect) in by_note {
+        writeln!(out, "{:>4} {:>6} {:>7} {:>7.1}%", full_note_name(note),
+                 rounds, perfect, perfect as f32 * 100.0 / rounds as f32)?;
+    }
+    let matrix = confusion(records);
+    if matrix.iter().flatten().all(|&x| x == 0) { return Ok(()) }
+    writeln!(out)?;
+    print_confusion(out, &matrix)?;
+    let mut mixups: Vec<(usize, usize, u32, u32)> = vec![];
+    for (played, row) in matrix.iter().enumerate() {
+        let total: u32 = row.iter().sum();
+        for (guessed, &count) in row.iter().enumerate() {
+            if guessed != played && count > 0 {
+                mixups.push((played, guessed, count, total));
+            }
+        }
+    }
+    mixups.sort_by(|a, b| {
+        let a_rate = a.2 as f32 / a.3 as f32;
+        let b_rate = b.2 as f32 / b.3 as f32;
+        b_rate.partial_cmp(&a_rate).unwrap().then(b.2.cmp(&a.2))
+    });
+    for &(played, guessed, count, total) in mixups.iter().take(MIXUP_COUNT) {
+        writeln!(out, "You guess {} for {} {:.0}% of the time ({} of {}).",
+                 note_names()[guessed], note_names()[played],
+                 count as f32 * 100.0 / total as f32, count, total)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(score: u32, rounds: &[(u32, Outcome)]) -> SessionRecord {
+        let count = |outcome| rounds.iter().filter(|x| x.1 == outcome)
+            .count() as u32;
+        SessionRecord {
+            time: 0, mode: Mode::Note, min_note: 60, max_note: 72,
+            rounds: rounds.len() as u32, perfect: count(Outcome::Perfect),
+            wrong_octave: count(Outcome::WrongOctave),
+            hedged: count(Outcome::Hedged), score,
+            round_records: rounds.iter().map(|&(note, outcome)| RoundRecord {
+                notes: vec![note], outcome, reflection: None, guesses: vec![],
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn no_sessions() {
+        assert_eq!(lifetime_stats(&[]), LifetimeStats {
+            sessions: 0, notes_tested: 0, accuracy: 0.0, best_score: 0,
+            trend: None, hardest: vec![],
+        });
+    }
+
+    #[test]
+    fn one_session() {
+        let records = [session(63, &[(60, Outcome::Perfect),
+                                     (61, Outcome::Missed),
+                                     (73, Outcome::WrongOctave),
+                                     (62, Outcome::Perfect)])];
+        assert_eq!(lifetime_stats(&records), LifetimeStats {
+            sessions: 1, notes_tested: 4, accuracy: 50.0, best_score: 63,
+            trend: None, hardest: vec![(1, 2, 2)],
+        });
+    }
+
+    #[test]
+    fn best_trend_and_hardest() {
+        // Five sessions at 50, then five at 40, 50, 60, 70, and 80.
+        let records: Vec<SessionRecord> = [50, 50, 50, 50, 50,
+                                           40, 50, 60, 70, 80].iter()
+            .map(|&score| session(score, &[(60, Outcome::Perfect),
+                                           (62, Outcome::Missed)]))
+            .chain([session(90, &[(64, Outcome::Perfect),
+                                  (64, Outcome::Missed),
+                                  (65, Outcome::Missed),
+                                  (65, Outcome::Perfect),
+                                  (65, Outcome::Missed)])])
+            .collect();
+        let stats = lifetime_stats(&records);
+        assert_eq!(stats.sessions, 11);
+        assert_eq!(stats.notes_tested, 25);
+        assert_eq!(stats.accuracy, 48.0);
+        assert_eq!(stats.best_score, 90);
+        // The last five average 70, against 50 for the first five.
+        assert_eq!(stats.trend, Some(20.0));
+        // D missed every time, then F two times in three, then E one in two.
+        assert_eq!(stats.hardest, vec![(2, 10, 10), (5, 3, 2), (4, 2, 1)]);
+    }
+}
@@ -0,0 +1,77 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// The largest interval we'll ask about, in semitones. (An octave.)
+pub const MAX_INTERVAL: u32 = 12;
+
+/// Short names of the simple intervals, indexed by size in semitones.
+const INTERVAL_NAMES: &[&str] = &["P1","m2","M2","m3","M3","P4","TT",
+                                  "P5","m6","M6","m7","M7","P8"];
+
+/// The broad quality of each simple interval, indexed by size in semitones.
+const INTERVAL_QUALITIES: &[&str] = &["perfect","minor","major","minor",
+                                      "major","perfect","tritone","perfect",
+                                      "minor","major","minor","major",
+                                      "perfect"];
+
+static VALID_INTERVAL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^([PmMAd])([1-8])$|^TT$"#).unwrap()
+});
+
+pub fn interval_name(semitones: u32) -> &'static str {
+    INTERVAL_NAMES[semitones as usize]
+}
+
+pub fn interval_quality(semitones: u32) -> &'static str {
+    INTERVAL_QUALITIES[semitones as usize]
+}
+
+/// Parses the broad quality of an interval: `perfect`, `major`, `minor`, or
+/// `tritone`. `P`, `M`, `m`, and `TT` work too.
+pub fn parse_interval_quality(name: &str) -> Option<&'static str> {
+    match name {
+        "P" => return Some("perfect"),
+        "M" => return Some("major"),
+        "m" => return Some("minor"),
+        "TT" => return Some("tritone"),
+        _ => (),
+    }
+    let name = name.to_ascii_lowercase();
+    INTERVAL_QUALITIES.iter().find(|&&quality| quality == name).copied()
+}
+
+/// Parses an interval name like `m3`, `P5`, or `TT` into a size in
+/// semitones. Augmented and diminished spellings (`A4`, `d5`, etc.) are
+/// accepted too, as long as they work out to something between a unison and
+/// an octave.
+pub fn parse_interval(name: &str) -> Option<u32> {
+    let captures = VALID_INTERVAL_PATTERN.captures(name)?;
+    let (quality, number) = match (captures.get(1), captures.get(2)) {
+        (Some(quality), Some(number)) => (quality.as_str(),
+                                          number.as_str().parse::<u32>()
+                                          .unwrap()),
+        _ => return Some(6), // TT
+    };
+    // The size of the major (or perfect) version of each interval number.
+    let (base, perfect): (i32, bool) = match number {
+        1 => (0, true),
+        2 => (2, false),
+        3 => (4, false),
+        4 => (5, true),
+        5 => (7, true),
+        6 => (9, false),
+        7 => (11, false),
+        8 => (12, true),
+        _ => unreachable!(),
+    };
+    let size = match (quality, perfect) {
+        ("P", true) | ("M", false) => base,
+        ("m", false) => base - 1,
+        ("A", _) => base + 1,
+        ("d", true) => base - 1,
+        ("d", false) => base - 2,
+        _ => return None, // e.g. "P3" or "M5"
+    };
+    if size < 0 || size > MAX_INTERVAL as i32 { None }
+    else { Some(size as u32) }
+}
@@ -0,0 +1,58 @@
+use crate::{NOTES_PER_OCTAVE, full_note_name, glyphs::glyphs, naming};
+
+/// For each pitch class starting at C: which white key it is (or, for a black
+/// key, which white key it sits to the right of), and whether it's black.
+const KEY_LAYOUT: &[(usize, bool)] = &[(0,false),(0,true),(1,false),(1,true),
+                                       (2,false),(3,false),(3,true),(4,false),
+                                       (4,true),(5,false),(5,true),(6,false)];
+const WHITE_KEYS: usize = 7;
+const WHITE_KEY_WIDTH: usize = 4;
+/// Rows of the keyboard where the black keys are.
+const BLACK_KEY_ROWS: usize = 3;
+/// Rows of the keyboard below the black keys, not counting the bottom edge.
+const WHITE_KEY_ROWS: usize = 1;
+
+/// Draws one octave of piano keyboard, from C to B, with `note`'s key filled
+/// in and pointed out underneath.
+pub fn render_keyboard(note: u32) -> String {
+    let (marked_white, marked_black) = KEY_LAYOUT[(note % NOTES_PER_OCTAVE)
+                                                  as usize];
+    let glyphs = glyphs();
+    let width = WHITE_KEYS * WHITE_KEY_WIDTH + 1;
+    let mut rows = vec![];
+    for row in 0 .. BLACK_KEY_ROWS + WHITE_KEY_ROWS + 1 {
+        let mut line: Vec<char> = (0 .. width).map(|column| {
+            let white = column / WHITE_KEY_WIDTH;
+            if column % WHITE_KEY_WIDTH == 0 { '|' }
+            else if !marked_black && white == marked_white {
+                glyphs.marked_key
+            }
+            else if row == BLACK_KEY_ROWS + WHITE_KEY_ROWS { '_' }
+            else { ' ' }
+        }).collect();
+        if row < BLACK_KEY_ROWS {
+            for (white, black) in KEY_LAYOUT.iter() {
+                if !black { continue }
+                let center = (white + 1) * WHITE_KEY_WIDTH;
+                let fill = if marked_black && *white == marked_white {
+                    glyphs.marked_key
+                } else { glyphs.black_key };
+                for c in &mut line[center - 1 ..= center + 1] { *c = fill }
+            }
+        }
+        rows.push(line.into_iter().collect::<String>());
+    }
+    let letters: String = (0 .. width).map(|column| {
+        if column % WHITE_KEY_WIDTH == WHITE_KEY_WIDTH / 2 {
+            naming::letters()[column / WHITE_KEY_WIDTH].chars().next()
+                .unwrap()
+        }
+        else { ' ' }
+    }).collect();
+    rows.push(letters.trim_end().to_string());
+    let marker_column = if marked_black { (marked_white + 1) * WHITE_KEY_WIDTH }
+    else { marked_white * WHITE_KEY_WIDTH + WHITE_KEY_WIDTH / 2 };
+    rows.push(format!("{}^ {}", " ".repeat(marker_column),
+                      full_note_name(note)));
+    rows.join("\n")
+}
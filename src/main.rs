@@ -1,25 +1,145 @@
 use std::{
-    io::{BufRead, stdin},
-    process::Command,
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind, Read, Stdin, Write, stdin, stdout},
+    sync::atomic::{AtomicBool, Ordering},
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use clap::{
+    ArgEnum, ArgMatches, Command as ClapCommand, CommandFactory, FromArgMatches,
+    Parser, ValueSource,
 };
-use clap::Parser;
 use once_cell::sync::Lazy;
-use rand::{Rng, thread_rng};
+use rand::{
+    Rng, SeedableRng,
+    distributions::{Distribution, WeightedIndex},
+    seq::index::sample,
+};
+use rand_chacha::ChaCha12Rng;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[macro_use]
+mod verbosity;
+mod interval;
+use interval::*;
+mod checkpoint;
+use checkpoint::Checkpoint;
+mod chord;
+use chord::*;
+mod config;
+mod database;
+mod events;
+use events::Events;
+mod glyphs;
+use glyphs::glyphs;
+mod history;
+use history::Outcome;
+mod keyboard;
+mod midi;
+mod naming;
+use naming::{Naming, OctaveConvention, note_names};
+mod note_weights;
+use note_weights::NoteWeights;
+mod pitch;
+mod playback;
+mod prerender;
+mod preset;
+mod progression;
+use preset::{Preset, PresetName};
+mod report;
+use report::*;
+mod review;
+mod scale;
+mod spelling;
+use spelling::Spelling;
+mod staff;
+mod stream;
+mod tuning;
+use tuning::{PitchStandard, Temperament, freqs};
 
 const NOTE_NAMES: &[&str] = &["C","C#","D","D#","E",
                               "F","F#","G","G#","A","A#","B"];
 const NOTES_PER_OCTAVE: u32 = 12;
 const MIDDLE_C: u32 = 60;
 const OCTAVE_OFFSET: u32 = 2;
-// A440
+/// The lowest and highest notes we can name (C0 and G8). This is the range of
+/// MIDI notes, minus the octaves below C0.
+const LOWEST_NOTE: u32 = OCTAVE_OFFSET * NOTES_PER_OCTAVE;
+const HIGHEST_NOTE: u32 = 127;
+// A440, unless `--tuning` or `--pitch-standard` says otherwise
 const BASE_NOTE: f32 = 69.0;
 const BASE_FREQ: f32 = 440.0;
+const MELODY_LENGTH: usize = 4;
+/// The longest melody `--melody-length` allows.
+const MAX_MELODY_LENGTH: usize = 16;
+/// With `--allow-hedge`, how many notes you can guess at once.
+const MAX_HEDGE_GUESSES: usize = 2;
+/// With `--neighbor-drill`, how much more likely the neighbors of a missed
+/// note are to be picked, and for how many rounds.
+const NEIGHBOR_DRILL_WEIGHT: u32 = 4;
+const NEIGHBOR_DRILL_ROUNDS: u32 = 5;
+/// How long to play a chord, or each note of an arpeggio, as a multiple of
+/// `--duration`.
+const CHORD_LENGTH: f32 = 1.5;
+const ARPEGGIO_NOTE_LENGTH: f32 = 0.5;
+/// With `--waveform square` or `sawtooth`, notes with a fundamental above
+/// this (in Hz) are low-passed at the cutoff, and have their volume scaled by
+/// the gain. See `start_freqs`.
+const ANTIALIAS_THRESHOLD: f32 = 1000.0;
+const ANTIALIAS_CUTOFF: f32 = 8000.0;
+const ANTIALIAS_GAIN: f32 = 0.7;
+/// With `--portamento`, how long each glide between notes takes, in seconds.
+/// (Very short notes get shorter glides.)
+const PORTAMENTO_TIME: f32 = 0.15;
+/// With `--loop-note`, the silence between repeats of the note, in seconds.
+const LOOP_GAP: f32 = 0.5;
+/// With `--loop-note`, how much the looping note is turned down.
+const LOOP_GAIN: f32 = 0.5;
+/// With `--smart-attempts`, notes below C2 or above C5 get an extra guess.
+const SMART_LOW_NOTE: u32 = (2 + OCTAVE_OFFSET) * NOTES_PER_OCTAVE;
+const SMART_HIGH_NOTE: u32 = (5 + OCTAVE_OFFSET) * NOTES_PER_OCTAVE;
+/// The range of frequencies we'll play, in Hz: roughly what people can hear.
+/// See `--out-of-range`.
+const MIN_AUDIBLE_FREQ: f32 = 20.0;
+const MAX_AUDIBLE_FREQ: f32 = 20000.0;
+/// The shortest `--duration` we allow, in seconds.
+const MIN_DURATION: f32 = 0.05;
+/// The longest fade-in we give a note, in seconds. Short notes get a shorter
+/// one.
+const MAX_FADE_IN: f32 = 0.1;
+/// How long we listen for in sing mode, in seconds.
+const SING_DURATION: f32 = 2.0;
+/// In detune mode, how likely a note is to be played in tune.
+const IN_TUNE_CHANCE: f64 = 1.0 / 3.0;
+/// In detune mode, the fewest cents out of tune a note that isn't in tune can
+/// be.
+const MIN_DETUNE: u32 = 5;
+/// The most lenient `--tolerance-cents` we allow. Any more and you'd be
+/// closer to the next note over.
+const MAX_TOLERANCE_CENTS: f32 = 100.0;
+/// Beats of clicks before a melody starts, when there's a metronome.
+const METRONOME_COUNT_IN: u32 = 4;
 
+/// A note name, with up to two accidentals (`C#`, `Db`, `Ebb`, `Fx`, `G♭`),
+/// and maybe an octave. The name starts with a capital letter, but it's up
+/// to `Spelling::parse_prefix` to say whether it's a note, since that
+/// depends on `--naming`.
 static VALID_NOTE_PATTERN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"^([ACDFG]#?|[BE])(-?[0-9]+)$"#).unwrap()
+    Regex::new(r#"^([A-Z][a-z]?[a-z]?[#bx♯♭𝄪𝄫]{0,2})(-?[0-9]+)?$"#).unwrap()
+});
+/// A note written the Helmholtz way, like `c#'` or `Bb,`.
+static HELMHOLTZ_NOTE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^([A-Za-z][a-z]?[a-z]?[#bx♯♭𝄪𝄫]{0,2})(['′″,]*)$"#).unwrap()
 });
 
+/// A few options can also be given by environment variable, for setting
+/// machine-wide defaults: WHAT_NOTE_MODE, WHAT_NOTE_DURATION,
+/// WHAT_NOTE_WAVEFORM, WHAT_NOTE_TEMPERAMENT, WHAT_NOTE_NAMING,
+/// WHAT_NOTE_PLAYBACK (or WHAT_NOTE_BACKEND), WHAT_NOTE_HISTORY,
+/// WHAT_NOTE_SCHEDULE, and WHAT_NOTE_CONFIG. Flags on the command line win.
 #[derive(Parser,Debug)]
 #[clap(author = "Solra Bizna <solra@bizna.name>", version,
        about = "Test and train your musical note distinguishingmentness!")]
@@ -27,180 +147,4391 @@ struct Invocation {
     /// Octave range. 1 = middle octave only. 2 = middle and below. 3 = middle
     /// and above. etc. Max = 5, min = 1.
     ///
-    /// Middle C is the one that's below the A that is 440Hz, and is C3.
+    /// Middle C is the one that's below the A that is 440Hz, and is C3 (see
+    /// `--octave-convention`).
     #[clap(short, default_value_t = 1)]
     octaves: u32,
+    /// Lowest note to play, e.g. "C2". Overrides `-o`.
+    #[clap(long, value_name = "NOTE")]
+    low: Option<String>,
+    /// Highest note to play, e.g. "G5". Overrides `-o`.
+    #[clap(long, value_name = "NOTE")]
+    high: Option<String>,
+    /// Only test the notes of this scale, e.g. "G major" or "D dorian".
+    /// Answers are spelled the way the scale spells them.
+    #[clap(long, value_name = "NAME", conflicts_with = "from-chord")]
+    from_scale: Option<String>,
+    /// Only test the notes of this chord, e.g. "C7" or "F#m". Answers are
+    /// spelled the way the chord spells them.
+    #[clap(long, value_name = "NAME")]
+    from_chord: Option<String>,
+    /// With `--from-scale` or `--from-chord`, only accept notes spelled the
+    /// way the scale or chord spells them (e.g. "E#" in F# major, not "F").
+    #[clap(long)]
+    notation_strict: bool,
+    /// How many points a perfect answer is worth, for scoring.
+    #[clap(long, value_name = "POINTS",
+           default_value_t = DEFAULT_WEIGHTS.perfect)]
+    weight_perfect: u32,
+    /// How many points an answer with the right note but the wrong octave is
+    /// worth.
+    #[clap(long, value_name = "POINTS",
+           default_value_t = DEFAULT_WEIGHTS.octave)]
+    weight_octave: u32,
+    /// How many points a hedged answer (see `--allow-hedge`) is worth.
+    #[clap(long, value_name = "POINTS",
+           default_value_t = DEFAULT_WEIGHTS.hedge)]
+    weight_hedge: u32,
+    /// Instead of `--weight-octave`, give the wrong octave more credit the
+    /// closer it was: 3/4 of a perfect answer for one octave off, 1/2 for
+    /// two, 1/4 for three, and none for more.
+    #[clap(long, conflicts_with = "weight-octave")]
+    octave_distance_credit: bool,
+    /// Count each answer by how hard its note is: more for black keys, and
+    /// more the further it is from the middle octave. The plain score is
+    /// shown too.
+    #[clap(long, conflicts_with_all = &["octave-distance-credit",
+                                        "sudden-death"])]
+    difficulty_weighted: bool,
+    /// After each round, print the score so far. (Not in blind mode.)
+    #[clap(long)]
+    running_score: bool,
+    /// Notes that have to come up at least once in the session, separated by
+    /// commas (e.g. "F#3,C4"). The rest of the notes are picked at random as
+    /// usual. (Only in note, octave, and sing modes.)
+    #[clap(long, value_name = "NOTES")]
+    must_include: Option<String>,
+    /// Don't play the same note as in any of the last few rounds (1, unless
+    /// you say otherwise). If there aren't enough notes to choose from,
+    /// repeats are allowed anyway. (Only in note, octave, and sing modes, and
+    /// the interval modes, where it's the interval that isn't repeated.)
+    #[clap(long, value_name = "ROUNDS", min_values = 0,
+           default_missing_value = "1")]
+    no_repeat: Option<usize>,
+    /// Give the whole session this many lives. Each wrong guess costs one,
+    /// and the session ends when they run out.
+    #[clap(long, value_name = "N")]
+    lives: Option<u32>,
+    /// Spell each black key with a sharp or a flat at random, so you see
+    /// both (e.g. "C#4" one time and "Db4" the next). Either spelling is
+    /// accepted as an answer.
+    #[clap(long)]
+    mixed_accidentals: bool,
+    /// In learn mode, also say each note's name out loud (with `espeak`).
+    #[clap(long)]
+    speak: bool,
+    /// In learn mode, how long to pause after each note, in seconds.
+    #[clap(long, value_name = "SECONDS", default_value_t = 1.0)]
+    gap: f32,
+    /// Wait a random time, up to this many milliseconds, before playing each
+    /// round, so you can't tell from the timing when it's coming.
+    #[clap(long, value_name = "MS", default_value_t = 0)]
+    jitter: u32,
+    /// Only print plain ASCII characters, for terminals that can't show
+    /// anything fancier.
+    #[clap(long)]
+    ascii: bool,
+    /// What to call the notes. In German, B♮ is "H" and B♭ is "B", so "H4"
+    /// is the note English calls "B4". `solfege` is fixed-do, so "Do#4" is
+    /// "C#4".
+    #[clap(long, arg_enum, default_value = "english", alias = "names",
+           env = "WHAT_NOTE_NAMING")]
+    naming: Naming,
+    /// How to write octaves, in what's printed, in what you type, and in this
+    /// help. `c3` calls middle C "C3"; `c4` calls it "C4", as scientific
+    /// pitch notation and most MIDI software do. `helmholtz` calls it "c'",
+    /// the C below it "c", and the C below that "C", then "C,", with sharps
+    /// and flats after the letter ("f#'"). Octave mode numbers octaves the
+    /// `c4` way under `helmholtz`.
+    #[clap(long, arg_enum, default_value = "c3", value_name = "CONVENTION")]
+    octave_convention: OctaveConvention,
+    /// Print less: no round separators or range info, just the essentials.
+    #[clap(short, long, parse(from_occurrences))]
+    quiet: u64,
+    /// Print more: how long each answer took, and the frequencies that were
+    /// played.
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u64,
+    /// Play a scale (C4 to C5) to check that sound works, and exit.
+    #[clap(long)]
+    test_audio: bool,
+    /// Before each guess, describe what you hear, in your own words. This
+    /// isn't scored, but it's kept with the round in the history file, and
+    /// listed at the end.
+    #[clap(long)]
+    reflect: bool,
+    /// Play a note in the left channel, then the right, to check that both
+    /// work (and aren't swapped), and exit.
+    #[clap(long, conflicts_with = "test-audio")]
+    test_stereo: bool,
+    /// Don't say what the lowest and highest notes are at the start.
+    #[clap(long)]
+    no_range_info: bool,
     /// Number of notes to test.
     #[clap(short, default_value_t = 20)]
     test_count: u32,
+    /// The most notes `-t` can ask for. More than this is cut down to it.
+    #[clap(long, value_name = "COUNT", default_value_t = 1000)]
+    max_test_count: u32,
+    /// Instead of a set number of notes, keep going until this many minutes
+    /// have passed. The round in progress when time runs out is finished.
+    #[clap(long, value_name = "MINUTES",
+           conflicts_with_all = &["resume", "must-include", "answers"])]
+    duration_minutes: Option<f32>,
+    /// Instead of a set number of notes, keep going until you've gotten this
+    /// many perfectly right (but no more than `--max-test-count` rounds).
+    #[clap(long, value_name = "COUNT",
+           conflicts_with_all = &["duration-minutes", "sudden-death",
+                                  "must-include", "answers"])]
+    until_correct: Option<u32>,
     /// Number of tries per note.
     #[clap(short, default_value_t = 3)]
     attempt_limit: u32,
+    /// Pick a random number of tries for each note, from 1 to the `-a`
+    /// limit.
+    #[clap(long)]
+    variable_attempts: bool,
+    /// Give an extra guess for notes that are harder to name: one for an
+    /// accidental, and one for a note below C2 or above C5.
+    #[clap(long)]
+    smart_attempts: bool,
+    /// If the final score falls just short of a grade, play one more round,
+    /// with one guess. Get it right and you get the higher grade; otherwise
+    /// the grade stands.
+    #[clap(long, conflicts_with_all = &["blind", "duration-minutes"])]
+    sudden_death: bool,
+    /// Don't say whether each guess was right. You get one guess per round,
+    /// and see how you did at the end.
+    #[clap(long)]
+    blind: bool,
+    /// What to test. `note` plays a note and asks you to name it. `octave`
+    /// tells you the note's name, plays it, and asks which octave it's in.
+    /// `interval` plays two notes and asks for the interval between them
+    /// (e.g. "m3" or "P5"). `theory-interval` prints two note names and asks
+    /// you for the interval between them, without playing anything.
+    /// `interval-quality` plays two notes and asks only whether the interval
+    /// between them is perfect, major, minor, or a tritone. `melody` plays a
+    /// short melody (see `--melody-length`) and asks you for all of its
+    /// notes, with partial credit for each one in the right place if you
+    /// miss. `sing` shows you a note name and listens (through `rec`) while
+    /// you sing or play it. `inversion` plays a triad and asks whether it's
+    /// in root position, 1st inversion, or 2nd inversion. `chord-quality`
+    /// plays a triad or 7th chord and asks for its quality: maj, min, dim,
+    /// aug, dom7, maj7, or min7. `duel` is note mode for two players (see
+    /// `--players`), taking turns at each note. `learn` just plays notes and
+    /// tells you their names, with no guessing and no score. `progression`
+    /// plays a few chords in a key (from `--from-scale`, or a random major
+    /// key), and asks for their roman numerals (e.g. "I vi IV V").
+    /// `frequency` plays a note and asks for its frequency in Hz (see
+    /// `--freq-tolerance`). `detune` plays a note a little out of tune (see
+    /// `--max-detune`), or in tune, and asks whether it's sharp, flat, or in
+    /// tune, and optionally by how many cents. `mixed` goes back and forth
+    /// between note mode and sing mode, one round each. `count-notes` plays a
+    /// few notes at once and asks how many there are (see `--max-cluster`).
+    #[clap(short, long, arg_enum, default_value = "note",
+           env = "WHAT_NOTE_MODE")]
+    mode: Mode,
+    /// In duel mode, the players' names, separated by commas.
+    #[clap(long, value_name = "NAMES", default_value = "Player 1,Player 2")]
+    players: String,
+    /// Only ask for the note name, not the octave. (You may still type the
+    /// octave if you like, but it won't be checked.)
+    #[clap(long)]
+    ignore_octave: bool,
+    /// Don't show which round we're on.
+    #[clap(long)]
+    no_progress: bool,
+    /// In melody mode, play the melody in time with a metronome at this many
+    /// beats per minute.
+    #[clap(long, value_name = "BPM")]
+    metronome: Option<u32>,
+    /// In melody mode, glide smoothly from each note to the next, like a
+    /// voice or a violin, instead of stepping.
+    #[clap(long)]
+    portamento: bool,
+    /// At the end of the session, show which notes you mistook for which.
+    /// (Only in note mode.)
+    #[clap(long)]
+    confusion: bool,
+    /// Write that same table to this file, as CSV.
+    #[clap(long, value_name = "PATH")]
+    confusion_file: Option<PathBuf>,
+    /// Play a chime when you get a note right, and a buzz when you run out
+    /// of guesses.
+    #[clap(long)]
+    feedback_sounds: bool,
+    /// How long to play each note, in seconds. Short notes are harder to
+    /// pick out. (Chords last a bit longer. With `--metronome`, melody notes
+    /// last a beat instead.)
+    #[clap(long, value_name = "SECONDS", default_value_t = 1.0,
+           env = "WHAT_NOTE_DURATION")]
+    duration: f32,
+    /// What kind of wave to play notes with. Square and sawtooth waves are
+    /// buzzier, and easier for some people to pitch.
+    #[clap(long, arg_enum, default_value = "sine",
+           env = "WHAT_NOTE_WAVEFORM")]
+    waveform: Waveform,
+    /// Semitones to shift everything by as it's played, for practicing with
+    /// a transposing instrument (e.g. -2 for a B♭ clarinet, whose written C
+    /// sounds as a B♭).
+    #[clap(long, value_name = "SEMITONES", default_value_t = 0,
+           allow_hyphen_values = true)]
+    transpose: i32,
+    /// With `--transpose`, whether you answer with the written note or the
+    /// note that actually sounds.
+    #[clap(long, arg_enum, default_value = "written")]
+    transpose_target: TransposeTarget,
+    /// What to do when a note (after `--transpose`) would be too high or too
+    /// low to hear: `fold` it into range by octaves, `clamp` it to the
+    /// nearest frequency in range, or `skip` it and play silence.
+    #[clap(long, arg_enum, default_value = "fold")]
+    out_of_range: OutOfRange,
+    /// Don't filter high square and sawtooth notes. They'll be louder and
+    /// harsher, with aliasing.
+    #[clap(long)]
+    no_antialias: bool,
+    /// Play chords one note at a time, instead of all at once.
+    #[clap(long)]
+    arpeggiate: bool,
+    /// How to tune notes. `equal` is equal temperament, like a piano. `just`
+    /// tunes every note to a pure ratio above the tonic (see `--tonic`), and
+    /// every note of a chord to a pure ratio above its root.
+    #[clap(long, arg_enum, default_value = "equal",
+           env = "WHAT_NOTE_TEMPERAMENT")]
+    temperament: Temperament,
+    /// With `--temperament just`, the pitch class to tune from, e.g. "D" or
+    /// "Bb". Defaults to the tonic of `--from-scale` (or `--from-chord`), or
+    /// else the lowest note.
+    #[clap(long, value_name = "NOTE")]
+    tonic: Option<String>,
+    /// What A3 (the A above middle C) is tuned to, by name: `a440` is the
+    /// modern standard, `baroque-415` and `classical-430` are historical.
+    #[clap(long, arg_enum, value_name = "STANDARD", default_value = "a440")]
+    pitch_standard: PitchStandard,
+    /// What A3 is tuned to, in Hz. This beats `--pitch-standard`. (`--a4`
+    /// works too, since that's what scientific pitch notation calls it.)
+    #[clap(long, value_name = "HZ", alias = "a4")]
+    tuning: Option<f32>,
+    /// At the end of the session, show how well (and how quickly) you did on
+    /// each pitch class. (Only in note mode.)
+    #[clap(long)]
+    breakdown: bool,
+    /// After each note, show where it is on a piano keyboard.
+    #[clap(long)]
+    keyboard: bool,
+    /// In note mode, show each note on a staff as it's played, for practice
+    /// connecting the written note to its sound and its name.
+    #[clap(long)]
+    staff: bool,
+    /// In note mode, let you hedge by guessing two notes at once, separated
+    /// by a comma (e.g. "C#4,D4"). If one of them is right, you get half
+    /// credit.
+    #[clap(long)]
+    allow_hedge: bool,
+    /// When you get the note right but the octave wrong, play the note again
+    /// in the octave you guessed, so you can hear that the note itself was
+    /// right. (Only in note mode.)
+    #[clap(long)]
+    reinforce_octave: bool,
+    /// When you name the wrong note, say how many semitones off it was, and
+    /// which way. (Only in note mode.) The same as `--feedback full`.
+    #[clap(long)]
+    distance_hint: bool,
+    /// How much to say after a wrong guess: `minimal` says to try again,
+    /// `full` also says how far off it was (see `--distance-hint`), and
+    /// `none` says nothing until the round is over.
+    #[clap(long, arg_enum, value_name = "LEVEL", default_value = "minimal")]
+    feedback: Feedback,
+    /// In the interval modes, always start from this note, e.g. "C4", and
+    /// only vary the second one.
+    #[clap(long, value_name = "NOTE")]
+    fixed_root: Option<String>,
+    /// In the interval modes, whether the second note is above the first
+    /// (`up`), below it (`down`), or either.
+    #[clap(long, arg_enum, value_name = "DIRECTION", default_value = "both")]
+    interval_direction: IntervalDirection,
+    /// In count-notes mode, the most notes to play at once. (The fewest is
+    /// two.)
+    #[clap(long, value_name = "COUNT", default_value_t = 4)]
+    max_cluster: usize,
+    /// In melody mode, how many notes each melody has.
+    #[clap(long, value_name = "COUNT", default_value_t = MELODY_LENGTH)]
+    melody_length: usize,
+    /// Keep playing the note, quietly, over and over until you answer,
+    /// instead of only when you ask with "?". (Only in note and duel modes.)
+    #[clap(long)]
+    loop_note: bool,
+    /// In note mode, how many times per session you can type "prev" to hear
+    /// the previous round's note again. (As many as you like, without this.
+    /// Never, in blind mode.)
+    #[clap(long, value_name = "N")]
+    prev_limit: Option<u32>,
+    /// When you run out of guesses, play the note again and say its name out
+    /// loud (with `espeak`), to drill the two together. (Only in note mode.)
+    #[clap(long)]
+    reinforce_miss: bool,
+    /// After you miss a note, test the notes a semitone or two away from it
+    /// more often for the next few rounds.
+    #[clap(long)]
+    neighbor_drill: bool,
+    /// Seed for picking notes. The same seed (and settings) always gives the
+    /// same notes in the same order.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Instead of playing anything, grade the answers in this file (one per
+    /// line, one line per note) against the notes that would have been
+    /// played. Use with `--seed`.
+    #[clap(long, value_name = "PATH")]
+    answers: Option<PathBuf>,
+    /// Instead of playing anything, write a numbered worksheet of the rounds
+    /// a session with this `--seed` would have, for filling in on paper, and
+    /// (in a separate file, with "-key" added to the name) its answer key.
+    /// Then run a session with the same seed and settings to play the notes.
+    #[clap(long, value_name = "PATH",
+           conflicts_with_all = &["answers", "resume", "duration-minutes"])]
+    worksheet: Option<PathBuf>,
+    /// Pick notes according to the weights in this file: one note (`F#3`) or
+    /// pitch class (`F#`) per line, then its weight. A note with weight 2 is
+    /// twice as likely as one with weight 1, and one with weight 0 never
+    /// comes up. (Note, octave, sing, melody, duel, and learn modes.)
+    #[clap(long = "weights", value_name = "PATH")]
+    note_weights: Option<PathBuf>,
+    /// Give notes that aren't in the `--weights` file a weight of 0, instead
+    /// of 1.
+    #[clap(long, requires = "note-weights")]
+    weights_exclusive: bool,
+    /// Only play the notes you missed in your last session (as recorded in
+    /// the history file). (Same modes as `--weights`.)
+    #[clap(long, conflicts_with = "note-weights")]
+    retry_last: bool,
+    /// Schedule notes SM-2 style: the ones you miss come up again sooner,
+    /// and the ones you know come up less and less often, across sessions.
+    /// (Only in note, octave, and sing modes.)
+    #[clap(long, conflicts_with_all = &["note-weights", "retry-last"])]
+    spaced_repetition: bool,
+    /// File to keep the `--spaced-repetition` schedule in. Defaults to
+    /// `schedule.json`, next to the history file.
+    #[clap(long, value_name = "PATH", env = "WHAT_NOTE_SCHEDULE")]
+    schedule: Option<PathBuf>,
+    /// File to record each session's results in. Defaults to
+    /// `$XDG_DATA_HOME/what-note/history.jsonl` (usually
+    /// `~/.local/share/what-note/history.jsonl`).
+    #[clap(long, value_name = "PATH", env = "WHAT_NOTE_HISTORY")]
+    history: Option<PathBuf>,
+    /// Read guesses from this file or named pipe instead of the terminal,
+    /// one per line. For graphical frontends.
+    #[clap(long, value_name = "PATH")]
+    input_fd: Option<PathBuf>,
+    /// Also take guesses from this raw MIDI device (e.g.
+    /// "/dev/snd/midiC1D0"): press the key for the note instead of typing
+    /// it. (Only in note and duel modes.)
+    #[clap(long, value_name = "DEVICE")]
+    midi_in: Option<PathBuf>,
+    /// Report each round's progress and result to this file or named pipe,
+    /// one event per line, for frontends. The lines look like "round 2 10",
+    /// "result 2 perfect C#4", and, at the end, "done 85".
+    #[clap(long, value_name = "PATH")]
+    output_fd: Option<PathBuf>,
+    /// Don't record this session in the history file.
+    #[clap(long)]
+    no_history: bool,
+    /// Also record each session in this SQLite database, creating it if
+    /// needed. (This needs the `sqlite3` program.)
+    #[clap(long, value_name = "PATH")]
+    db: Option<PathBuf>,
+    /// Record each round in the database too, not just each session.
+    #[clap(long, requires = "db")]
+    db_rounds: bool,
+    /// Pick up the last session where it left off, if it was interrupted.
+    /// Its mode, range, and number of notes are used, instead of whatever
+    /// was given this time.
+    #[clap(long)]
+    resume: bool,
+    /// Throw away the interrupted session, if there is one. (Otherwise it's
+    /// kept until a new session gets through its first round.)
+    #[clap(long, conflicts_with = "resume")]
+    discard: bool,
+    /// Instead of running a session, show your lifetime statistics from the
+    /// history file.
+    #[clap(long)]
+    lifetime: bool,
+    /// Instead of running a session, show how often you've gotten each note
+    /// right, and which notes you mistake for which, from every note-mode
+    /// guess in the history file.
+    #[clap(long)]
+    stats: bool,
+    /// Instead of running a session, show a little graph of your scores in
+    /// the last few sessions (20, unless you say otherwise).
+    #[clap(long, value_name = "SESSIONS", min_values = 0,
+           default_missing_value = "20")]
+    trend: Option<usize>,
+    /// Instead of running a session, show what every option is set to, and
+    /// whether that came from the command line, an environment variable, or
+    /// the defaults.
+    #[clap(long)]
+    explain_config: bool,
+    /// Read default options from this file, like `attempt-limit = 2` or
+    /// `low = "C3"`, one per line. Defaults to
+    /// `$XDG_CONFIG_HOME/what-note/config.toml` (usually
+    /// `~/.config/what-note/config.toml`), if it's there. Options given on the
+    /// command line or in the environment beat the ones in the file, which
+    /// beat `--preset`.
+    #[clap(long, value_name = "PATH", env = "WHAT_NOTE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Don't read the config file.
+    #[clap(long, conflicts_with = "config")]
+    no_config: bool,
+    /// Before starting, render every note in the range into one sound file
+    /// (with `sox`), and play notes from that instead of making each one as
+    /// it's needed. Chords and melodies are still made as they're needed.
+    #[clap(long)]
+    prerender: bool,
+    /// Keep one player running for the whole session, and feed each note
+    /// into it, instead of starting a new one for every note, so the sound
+    /// device is only opened once.
+    #[clap(long, conflicts_with = "prerender")]
+    low_latency: bool,
+    /// How to play notes: `native` makes the sound itself, and `sox` has SoX
+    /// make it. Either way, something else has to send it to the sound
+    /// device: `native` uses ALSA's `aplay` (from alsa-utils), or SoX's
+    /// `play` if there's no `aplay`. SoX is still needed for
+    /// `--portamento`, `--loop-note`, `--metronome`, `--feedback-sounds`,
+    /// `--test-stereo`, and `--prerender`, and for recording in sing mode.
+    #[clap(long, arg_enum, value_name = "BACKEND", default_value = "native",
+           env = "WHAT_NOTE_PLAYBACK")]
+    playback: playback::Backend,
+    /// Send notes to this raw MIDI device (e.g. "/dev/snd/midiC1D0"), for a
+    /// synth or keyboard to play, instead of making a sound. Chords are still
+    /// made as sound. (See `--list-midi`.)
+    #[clap(long, value_name = "DEVICE")]
+    midi_out: Option<PathBuf>,
+    /// List the raw MIDI devices that `--midi-out` can use, and exit.
+    #[clap(long)]
+    list_midi: bool,
+    /// Start from a bundle of settings suited to your level. Anything you set
+    /// yourself still wins. (See `--list-presets`.)
+    #[clap(long, arg_enum, value_name = "PRESET")]
+    preset: Option<PresetName>,
+    /// List the presets, and what each one sets.
+    #[clap(long)]
+    list_presets: bool,
+    /// Instead of running a session, list every note that could come up with
+    /// these settings, and its frequency.
+    #[clap(long)]
+    print_notes: bool,
+    /// In sing mode, how many cents off you can be and still be right.
+    #[clap(long, value_name = "CENTS", default_value_t = 50.0)]
+    tolerance_cents: f32,
+    /// In frequency mode, how many percent off you can be and still be
+    /// right.
+    #[clap(long, value_name = "PERCENT", default_value_t = 5.0)]
+    freq_tolerance: f32,
+    /// In detune mode, the most cents out of tune a note can be played.
+    #[clap(long, value_name = "CENTS", default_value_t = 50)]
+    max_detune: u32,
+    /// In detune mode, if you say how many cents out of tune a note is, how
+    /// far off that can be and still be right.
+    #[clap(long, value_name = "CENTS", default_value_t = 10.0)]
+    detune_tolerance: f32,
+    /// In sing mode, save each recording in this directory, as a WAV file
+    /// named after the round, the note, and the attempt.
+    #[clap(long, value_name = "DIR")]
+    record_attempts: Option<PathBuf>,
+}
+
+impl Invocation {
+    fn weights(&self) -> Weights {
+        Weights { perfect: self.weight_perfect, octave: self.weight_octave,
+                  hedge: self.weight_hedge }
+    }
+    fn fixed_root(&self) -> Option<u32> {
+        self.fixed_root.as_deref().map(parse_bound)
+    }
+    fn round_shape(&self) -> RoundShape {
+        RoundShape { fixed_root: self.fixed_root(),
+                     direction: self.interval_direction,
+                     max_cluster: self.max_cluster,
+                     melody_length: self.melody_length }
+    }
+}
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Mode { Note, Octave, Interval, TheoryInterval, IntervalQuality, Melody,
+            Sing,
+            Inversion, ChordQuality, Duel, Learn, Progression, Frequency,
+            Detune, Mixed,
+            CountNotes }
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+enum Waveform { Sine, Triangle, Square, Sawtooth }
+
+impl Waveform {
+    fn sox_name(self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Square => "square",
+            Waveform::Sawtooth => "sawtooth",
+        }
+    }
+    /// Whether this waveform has strong enough high harmonics to alias.
+    fn is_rich(self) -> bool {
+        matches!(self, Waveform::Square | Waveform::Sawtooth)
+    }
+}
+
+/// How to play a note.
+#[derive(Clone,Copy,Debug,PartialEq)]
+struct Tone {
+    /// In seconds.
+    duration: f32,
+    waveform: Waveform,
+    /// See `--no-antialias`.
+    antialias: bool,
+    /// Semitones to shift everything by as it's played. See `--transpose`.
+    transpose: i32,
+    /// See `--out-of-range`.
+    out_of_range: OutOfRange,
+}
+
+impl Tone {
+    /// The frequency `freq` actually sounds at, after transposing.
+    fn sounding(self, freq: f32) -> f32 {
+        freq * (2.0f32).powf(self.transpose as f32 / NOTES_PER_OCTAVE as f32)
+    }
+    /// The frequency to really play for `freq`, after transposing and
+    /// bringing it into hearing range. `None` means not to play it.
+    fn audible(self, freq: f32) -> Option<f32> {
+        let freq = self.sounding(freq);
+        if (MIN_AUDIBLE_FREQ ..= MAX_AUDIBLE_FREQ).contains(&freq) {
+            return Some(freq)
+        }
+        let result = match self.out_of_range {
+            OutOfRange::Clamp => {
+                Some(freq.clamp(MIN_AUDIBLE_FREQ, MAX_AUDIBLE_FREQ))
+            },
+            OutOfRange::Fold => {
+                let mut folded = freq;
+                while folded < MIN_AUDIBLE_FREQ { folded *= 2.0 }
+                while folded > MAX_AUDIBLE_FREQ { folded *= 0.5 }
+                Some(folded)
+            },
+            OutOfRange::Skip => None,
+        };
+        if !WARNED_OUT_OF_RANGE.swap(true, Ordering::Relaxed) {
+            match result {
+                Some(x) => eprintln!("{:.1}Hz is out of hearing range, so \
+                                      it's played at {:.1}Hz instead.",
+                                     freq, x),
+                None => eprintln!("{:.1}Hz is out of hearing range, so it \
+                                   isn't played.", freq),
+            }
+        }
+        result
+    }
+}
+
+/// What to do with a note too high or low to hear.
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+enum OutOfRange {
+    /// Play the highest or lowest frequency we can instead.
+    Clamp,
+    /// Move it by octaves until it's in range.
+    Fold,
+    /// Play silence instead.
+    Skip,
+}
+
+/// So that only the first note out of hearing range gets a warning.
+static WARNED_OUT_OF_RANGE: AtomicBool = AtomicBool::new(false);
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+enum TransposeTarget { Written, Sounding }
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+enum Feedback { None, Minimal, Full }
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+enum IntervalDirection { Both, Up, Down }
+
+/// What `draw_round` picks, beyond the mode and range: see `--fixed-root`,
+/// `--interval-direction`, and `--max-cluster`.
+#[derive(Clone,Copy)]
+struct RoundShape {
+    fixed_root: Option<u32>,
+    direction: IntervalDirection,
+    max_cluster: usize,
+    melody_length: usize,
 }
 
-enum Guess { Wrong, WrongOctave, Perfect }
+/// For the modes that don't care about any of that.
+const PLAIN_ROUND: RoundShape = RoundShape {
+    fixed_root: None, direction: IntervalDirection::Both, max_cluster: 2,
+    melody_length: MELODY_LENGTH,
+};
+
+enum Guess { /// In note mode, this carries the wrong guess.
+             Wrong(Option<ParsedNote>),
+             /// With `--notation-strict`, the right pitch spelled the wrong
+             /// way. This carries the right spelling.
+             Misspelled(Spelling),
+             /// In note mode, this carries the guessed note, if its octave
+             /// was in range.
+             WrongOctave(Option<u32>),
+             Perfect,
+             /// One of several guesses (see `--allow-hedge`) was right.
+             Hedged,
+             /// Gave up on this round (in note mode, by typing "skip" or
+             /// entering nothing).
+             Skipped,
+             /// Input ran out. End the session early.
+             Quit }
 
 fn full_note_name(note: u32) -> String {
-    let octave = note / NOTES_PER_OCTAVE - OCTAVE_OFFSET;
+    let octave = (note / NOTES_PER_OCTAVE - OCTAVE_OFFSET) as i32;
     let note = note % NOTES_PER_OCTAVE;
-    format!("{}{}", NOTE_NAMES[note as usize], octave)
+    naming::with_octave(note_names()[note as usize], octave)
 }
 
+#[allow(clippy::useless_format)]
 fn note_name(note: u32) -> String {
     let note = note % NOTES_PER_OCTAVE;
-    format!("{}", NOTE_NAMES[note as usize])
+    format!("{}", note_names()[note as usize])
 }
 
-fn name_to_note(note_name: &str) -> Option<u32> {
-    match VALID_NOTE_PATTERN.captures(note_name) {
+/// A note as the user typed it. The octave may have been left off. It's
+/// numbered the `c3` way, whatever `--octave-convention` says.
+#[derive(Clone,Copy,Debug)]
+struct ParsedNote {
+    spelling: Spelling,
+    pitch_class: u32,
+    octave: Option<i16>,
+}
+
+impl ParsedNote {
+    /// The full note, if an octave was given and it's in range.
+    fn note(&self) -> Option<u32> {
+        let octave = self.octave?;
+        let full_result = (octave as i64 + OCTAVE_OFFSET as i64)
+            * NOTES_PER_OCTAVE as i64
+            + self.pitch_class as i64;
+        full_result.try_into().ok()
+    }
+}
+
+fn parse_note(note_name: &str) -> Option<ParsedNote> {
+    let helmholtz = naming::octave_convention() == OctaveConvention::Helmholtz;
+    let captures = if helmholtz {
+        HELMHOLTZ_NOTE_PATTERN.captures(note_name)?
+    }
+    else { VALID_NOTE_PATTERN.captures(note_name)? };
+    let name = captures.get(1).unwrap().as_str();
+    let (spelling, "") = Spelling::parse_prefix(name)? else { return None };
+    let octave: Option<i16> = match captures.get(2) {
+        _ if helmholtz => {
+            let marks = captures.get(2).map_or("", |x| x.as_str());
+            Some(naming::helmholtz_octave(name, marks)?.try_into().ok()?)
+        },
+        Some(octave) => Some(octave.as_str().parse::<i16>().ok()?
+                             .checked_sub(naming::octave_shift() as i16)?),
         None => None,
-        Some(captures) => {
-            let note_name = captures.get(1).unwrap();
-            let octave: i16 = captures.get(2).unwrap().as_str().parse().unwrap();
-            let mut note = None;
-            for (index, name) in NOTE_NAMES.iter().enumerate() {
-                if &note_name.as_str() == name {
-                    note = Some(index);
-                    break
-                }
-            }
-            let note = match note {
-                Some(x) => x,
-                None => return None, // should not happen
-            };
-            let full_result = (octave as i64 + OCTAVE_OFFSET as i64)
-                * NOTES_PER_OCTAVE as i64
-                + note as i64;
-            full_result.try_into().ok()
+    };
+    // `Cb4` is in the octave below `C4`, and `B#4` in the one above `B4`.
+    let octave_shift = spelling.semitones_above_c()
+        .div_euclid(NOTES_PER_OCTAVE as i32) as i16;
+    let octave = match octave {
+        Some(octave) => Some(octave.checked_add(octave_shift)?),
+        None => None,
+    };
+    Some(ParsedNote { spelling, pitch_class: spelling.pitch_class(), octave })
+}
+
+/// Parses a guess at a note. The octave is required unless `ignore_octave`.
+fn parse_guess(guess: &str, ignore_octave: bool) -> Option<ParsedNote> {
+    parse_note(guess).filter(|guess| ignore_octave || guess.octave.is_some())
+}
+
+/// Parses a guess at a melody: notes separated by whitespace, or run
+/// together (`C4D4E4`) if that can be split up unambiguously. Nothing at
+/// all isn't a melody.
+fn parse_melody(guess: &str, ignore_octave: bool) -> Option<Vec<ParsedNote>> {
+    let mut guesses = vec![];
+    for word in guess.split_whitespace() {
+        match parse_guess(word, ignore_octave) {
+            Some(guess) => guesses.push(guess),
+            None => guesses.extend(split_notes(word, ignore_octave)?),
+        }
+    }
+    if guesses.is_empty() { None } else { Some(guesses) }
+}
+
+/// Splits up notes that were typed with nothing between them. Each note
+/// starts with a capital letter, and nothing else in a note name is one, so
+/// there's only one way to split them. If any piece isn't a note, neither is
+/// the whole thing.
+fn split_notes(word: &str, ignore_octave: bool) -> Option<Vec<ParsedNote>> {
+    // Helmholtz names can start lowercase, but then there's a `'` (or
+    // nothing) before the next one.
+    let mut starts: Vec<usize> = word.char_indices()
+        .scan(None, |last, (index, x)| {
+            let after_mark = matches!(last.replace(x), Some('\'' | '′' | '″'))
+                && x.is_alphabetic();
+            Some((index, x.is_ascii_uppercase() || after_mark || index == 0))
+        })
+        .filter(|&(_, start)| start).map(|(index, _)| index).collect();
+    if starts.is_empty() { return None }
+    starts.push(word.len());
+    starts.windows(2)
+        .map(|x| parse_guess(&word[x[0] .. x[1]], ignore_octave))
+        .collect()
+}
+
+/// Splits up notes separated by commas. Helmholtz names use commas too, so
+/// then only a comma with another note after it separates (`C,,D` is `C,`
+/// and `D`).
+fn split_note_list(list: &str) -> Vec<&str> {
+    if naming::octave_convention() != OctaveConvention::Helmholtz {
+        return list.split(',').collect()
+    }
+    let mut names = vec![];
+    let mut start = 0;
+    for (index, _) in list.match_indices(',') {
+        if list[index + 1 ..].trim_start().starts_with(char::is_alphabetic) {
+            names.push(&list[start .. index]);
+            start = index + 1;
+        }
+    }
+    names.push(&list[start ..]);
+    names
+}
+
+/// Parses several comma-separated guesses at the same note, for
+/// `--allow-hedge`.
+fn parse_hedge(guess: &str, ignore_octave: bool) -> Option<Vec<ParsedNote>> {
+    let guesses: Vec<ParsedNote> = split_note_list(guess).into_iter()
+        .map(|guess| parse_guess(guess.trim(), ignore_octave))
+        .collect::<Option<_>>()?;
+    if guesses.len() > MAX_HEDGE_GUESSES { None } else { Some(guesses) }
+}
+
+fn judge_note(guess: &ParsedNote, note: u32, ignore_octave: bool) -> Guess {
+    if guess.pitch_class != note % NOTES_PER_OCTAVE {
+        Guess::Wrong(Some(*guess))
+    }
+    else if ignore_octave || guess.note() == Some(note) { Guess::Perfect }
+    else {
+        Guess::WrongOctave(guess.note()
+                           .filter(|x| (LOWEST_NOTE ..= HIGHEST_NOTE)
+                                   .contains(x)))
+    }
+}
+
+fn judge_melody(guesses: &[ParsedNote], notes: &[u32], ignore_octave: bool)
+                -> Guess {
+    if guesses.len() != notes.len() { return Guess::Wrong(None) }
+    let mut result = Guess::Perfect;
+    for (guess, &note) in guesses.iter().zip(notes.iter()) {
+        match judge_note(guess, note, ignore_octave) {
+            Guess::Perfect => (),
+            Guess::WrongOctave(_) => result = Guess::WrongOctave(None),
+            _ => return Guess::Wrong(None),
+        }
+    }
+    result
+}
+
+/// How many of `guesses` are the right note (in the right octave, unless
+/// `ignore_octave`) in the right place in the melody, for partial credit.
+fn melody_notes_right(guesses: &[ParsedNote], notes: &[u32],
+                      ignore_octave: bool) -> u32 {
+    guesses.iter().zip(notes.iter())
+        .filter(|&(guess, &note)| matches!(judge_note(guess, note,
+                                                      ignore_octave),
+                                           Guess::Perfect))
+        .count() as u32
+}
+
+/// Judges several guesses at the same note, taking the best of them. A right
+/// answer among them counts as hedged.
+fn judge_hedge(guesses: &[ParsedNote], note: u32, ignore_octave: bool)
+               -> Guess {
+    let mut result = Guess::Wrong(None);
+    for guess in guesses {
+        match judge_note(guess, note, ignore_octave) {
+            Guess::Perfect => return Guess::Hedged,
+            x @ Guess::WrongOctave(_) => result = x,
+            _ => (),
+        }
+    }
+    result
+}
+
+/// With `--transpose`, the other name for `note` (sounding, if the answers
+/// are written, and vice versa), to go after its usual one.
+fn transposed_name(invocation: &Invocation, note: u32) -> String {
+    let (other, label) = match invocation.transpose_target {
+        TransposeTarget::Written => (note as i32 + invocation.transpose,
+                                     "sounds as"),
+        TransposeTarget::Sounding => (note as i32 - invocation.transpose,
+                                      "written"),
+    };
+    if invocation.transpose == 0 || other < LOWEST_NOTE as i32
+        || other > HIGHEST_NOTE as i32 {
+        return String::new()
+    }
+    let name = if invocation.ignore_octave { note_name(other as u32) }
+    else { full_note_name(other as u32) };
+    format!(" ({} {})", label, name)
+}
+
+/// How `note` is spelled in `spellings` (see `--from-scale`), if it's there.
+fn spelling_in(spellings: Option<&[Spelling]>, note: u32)
+               -> Option<Spelling> {
+    spellings?.iter()
+        .find(|x| x.pitch_class() == note % NOTES_PER_OCTAVE).copied()
+}
+
+/// The octave number of `note`, which depends on how it's spelled. (`B#2` is
+/// the same note as `C3`.) It's numbered by `--octave-convention`.
+fn octave_of(note: u32, spelling: Option<Spelling>) -> i32 {
+    let octave = match spelling {
+        Some(spelling) => spelling.octave(note),
+        None => (note / NOTES_PER_OCTAVE) as i32 - OCTAVE_OFFSET as i32,
+    };
+    octave + naming::octave_shift()
+}
+
+/// Says how far off a wrong guess was, e.g. "2 semitones too high". Without
+/// an octave to go on, it's the nearest way around the octave.
+fn distance_hint(guess: &ParsedNote, note: u32, ignore_octave: bool)
+                 -> String {
+    let (distance, suffix) = match guess.note().filter(|_| !ignore_octave) {
+        Some(guessed) => (guessed as i32 - note as i32, ""),
+        None => {
+            let octave = NOTES_PER_OCTAVE as i32;
+            let distance = (guess.pitch_class as i32 - note as i32)
+                .rem_euclid(octave);
+            (if distance > octave / 2 { distance - octave } else { distance },
+             ", ignoring the octave")
+        },
+    };
+    let direction = if distance < 0 { "low" } else { "high" };
+    let plural = if distance.abs() == 1 { "" } else { "s" };
+    format!("That was {} semitone{} too {}{}.", distance.abs(), plural,
+            direction, suffix)
+}
+
+/// A note to show as an example of how to write one: C#4, the way
+/// `--octave-convention` writes it.
+fn example_note() -> String {
+    full_note_name(MIDDLE_C + NOTES_PER_OCTAVE + 1)
+}
+
+fn name_to_note(note_name: &str) -> Option<u32> {
+    parse_note(note_name)?.note()
+}
+
+/// The note closest to `freq`, if it's one we can name.
+fn nearest_note(freq: f32) -> Option<u32> {
+    let note = (BASE_NOTE + (freq / tuning::base_freq()).log2()
+                * NOTES_PER_OCTAVE as f32).round();
+    (note >= LOWEST_NOTE as f32 && note <= HIGHEST_NOTE as f32)
+        .then_some(note as u32)
+}
+
+fn note_freq(note: u32) -> f32 {
+    pitch_freq(note as f32 + tuning::just_offset(note))
+}
+
+/// Like `note_freq`, but `pitch` can be between notes: `69.5` is a quarter
+/// tone above A4.
+fn pitch_freq(pitch: f32) -> f32 {
+    tuning::base_freq() * (2.0f32).powf((pitch - BASE_NOTE)
+                              / (NOTES_PER_OCTAVE as f32))
+}
+
+/// Explains that we couldn't run one of SoX's programs, and exits.
+fn sox_failed(program: &str, error: std::io::Error) -> ! {
+    if error.kind() == std::io::ErrorKind::NotFound {
+        eprintln!("Couldn't find `{}`. Is SoX installed, and in your PATH?",
+                  program);
+    }
+    else {
+        eprintln!("Couldn't run `{}`: {}", program, error);
+    }
+    std::process::exit(1)
+}
+
+/// Plays a C major scale from C4 to C5, naming each note, so you can tell
+/// whether sound is coming out.
+fn test_audio(tone: Tone) {
+    let c4 = MIDDLE_C + NOTES_PER_OCTAVE;
+    for step in [0, 2, 4, 5, 7, 9, 11, 12] {
+        say!(Essential, "{}", full_note_name(c4 + step));
+        play_note(c4 + step, tone);
+    }
+}
+
+/// Plays A3 in only the left channel, then only the right, saying which.
+fn test_stereo(tone: Tone) {
+    let freq = note_freq(BASE_NOTE as u32);
+    for (side, remix) in [("left", ["1", "0"]), ("right", ["0", "1"])] {
+        say!(Essential, "Playing in the {} ear...", side);
+        let mut command = Command::new("play");
+        command.arg("-q").arg("-n")
+            .arg("synth").arg(format!("{}", tone.duration))
+            .arg(tone.waveform.sox_name()).arg(format!("{}", freq));
+        shape_tone(&mut command, freq, tone.duration, tone);
+        command.arg("remix").args(remix);
+        let _ = command.spawn().unwrap_or_else(|x| sox_failed("play", x))
+            .wait();
+    }
+}
+
+/// Starts playing a note, without waiting for it to finish.
+fn start_note(note: u32, tone: Tone) -> Child {
+    prerender::start(note, tone).unwrap_or_else(|| start_notes(&[note], tone))
+}
+
+/// Starts playing several notes at once, without waiting for them to finish.
+fn start_notes(notes: &[u32], tone: Tone) -> Child {
+    let freqs: Vec<f32> = notes.iter().map(|&note| note_freq(note)).collect();
+    start_freqs(&freqs, tone)
+}
+
+/// Starts playing tones at several frequencies at once, without waiting for
+/// them to finish.
+fn start_freqs(freqs: &[f32], tone: Tone) -> Child {
+    let audible: Vec<f32> = freqs.iter().filter_map(|&x| tone.audible(x))
+        .collect();
+    if audible.is_empty() { return start_silence(tone.duration) }
+    if let Some(child) = playback::start(&audible, tone) { return child }
+    match synth_command(None, freqs, tone) {
+        Some(mut command) => command.spawn()
+            .unwrap_or_else(|x| sox_failed("play", x)),
+        None => start_silence(tone.duration),
+    }
+}
+
+/// Plays tones at several frequencies at once, and waits for them to finish.
+fn play_freqs(freqs: &[f32], tone: Tone) {
+    if !stream::play(freqs, tone) {
+        let _ = start_freqs(freqs, tone).wait();
+    }
+}
+
+/// A command that plays tones at several frequencies at once, or `None` if
+/// none of them can be heard. With `output`, which gives the format, it's a
+/// `sox` command that writes them to its standard output instead.
+fn synth_command(output: Option<fn(&mut Command)>, freqs: &[f32], tone: Tone)
+                 -> Option<Command> {
+    let freqs: Vec<f32> = freqs.iter().filter_map(|&x| tone.audible(x))
+        .collect();
+    if freqs.is_empty() { return None }
+    let mut command = match output {
+        Some(format) => {
+            let mut command = Command::new("sox");
+            command.arg("-q").arg("-n");
+            format(&mut command);
+            command.arg("-");
+            command
         },
+        None => {
+            let mut command = Command::new("play");
+            command.arg("-q").arg("-n");
+            command
+        },
+    };
+    command.arg("synth").arg(format!("{}", tone.duration));
+    for &freq in freqs.iter() {
+        command.arg(tone.waveform.sox_name()).arg(format!("{}", freq));
+    }
+    if freqs.len() > 1 { command.arg("remix").arg("-"); }
+    let highest = freqs.iter().copied().fold(0.0, f32::max);
+    shape_tone(&mut command, highest, tone.duration, tone);
+    Some(command)
+}
+
+/// Starts playing nothing, for as long as a note would have lasted.
+fn start_silence(duration: f32) -> Child {
+    if let Some(child) = playback::start_silence(duration) { return child }
+    Command::new("play").arg("-q").arg("-n")
+        .arg("trim").arg("0").arg(format!("{}", duration))
+        .spawn().unwrap_or_else(|x| sox_failed("play", x))
+}
+
+/// Starts playing a melody as one continuous tone, gliding from each note to
+/// the next, for `--portamento`. Each note lasts `tone.duration`, counting
+/// half of the glides on either side of it.
+fn start_glide(notes: &[u32], tone: Tone) -> Child {
+    let duration = tone.duration;
+    let total = duration * notes.len() as f32;
+    let glide = PORTAMENTO_TIME.min(duration * 0.5);
+    // The bends are relative, so if the first note has to be moved into
+    // hearing range, the rest move with it.
+    let first = tone.sounding(note_freq(notes[0]));
+    let Some(shift) = tone.audible(note_freq(notes[0]))
+        .map(|x| x / first) else { return start_silence(total) };
+    let mut command = Command::new("play");
+    command.arg("-q").arg("-n")
+        .arg("synth").arg(format!("{}", total))
+        .arg(tone.waveform.sox_name())
+        .arg(format!("{}", first * shift));
+    if notes.len() > 1 {
+        command.arg("bend");
+        // Each bend is "delay,cents,length", and each delay is counted from
+        // the end of the bend before.
+        for (index, pair) in notes.windows(2).enumerate() {
+            let delay = if index == 0 { duration - glide * 0.5 }
+            else { duration - glide };
+            let cents = (pair[1] as f32 - pair[0] as f32)
+                * 1200.0 / NOTES_PER_OCTAVE as f32;
+            command.arg(format!("{},{},{}", delay, cents, glide));
+        }
+    }
+    let highest = notes.iter()
+        .map(|&note| tone.sounding(note_freq(note)) * shift)
+        .fold(0.0, f32::max);
+    finish_tone(command, highest, total, tone)
+}
+
+/// Adds the filtering, fades, and volume common to all our tones, then
+/// starts playing.
+fn finish_tone(mut command: Command, highest: f32, total: f32, tone: Tone)
+               -> Child {
+    shape_tone(&mut command, highest, total, tone);
+    command.spawn().unwrap_or_else(|x| sox_failed("play", x))
+}
+
+/// The part of `finish_tone` that adds the effects.
+fn shape_tone(command: &mut Command, highest: f32, total: f32, tone: Tone) {
+    let duration = tone.duration;
+    let mut volume = 0.6;
+    // SoX makes square and sawtooth waves naively, so high notes get
+    // harmonics above the Nyquist frequency, which fold back down as harsh
+    // inharmonic junk. Filter them off, and turn down a bit, because the
+    // filter's ringing overshoots.
+    if tone.antialias && tone.waveform.is_rich()
+        && highest > ANTIALIAS_THRESHOLD {
+        command.arg("lowpass").arg(format!("{}", ANTIALIAS_CUTOFF));
+        volume *= ANTIALIAS_GAIN;
+    }
+    // Keep the fades from overlapping, or SoX complains (and short notes
+    // click).
+    let fade_in = MAX_FADE_IN.min(duration * 0.25);
+    let fade_out = (duration * 0.7).min(duration - fade_in);
+    command.arg("fade").arg(format!("{}", fade_in))
+        .arg(format!("{}", total)).arg(format!("{}", fade_out))
+        .arg("vol").arg(format!("{:.2}", volume));
+}
+
+/// A note playing over and over, quietly, for `--loop-note`. It stops when
+/// this is dropped.
+struct LoopingNote(Child);
+
+impl LoopingNote {
+    fn start(note: u32, tone: Tone) -> LoopingNote {
+        let Some(freq) = tone.audible(note_freq(note)) else {
+            return LoopingNote(start_silence(0.0))
+        };
+        let mut command = Command::new("play");
+        command.arg("-q").arg("-n")
+            .arg("synth").arg(format!("{}", tone.duration))
+            .arg(tone.waveform.sox_name()).arg(format!("{}", freq));
+        shape_tone(&mut command, freq, tone.duration, tone);
+        command.arg("vol").arg(format!("{:.2}", LOOP_GAIN))
+            .arg("pad").arg("0").arg(format!("{}", LOOP_GAP))
+            .arg("repeat").arg("-");
+        LoopingNote(command.spawn()
+                    .unwrap_or_else(|x| sox_failed("play", x)))
+    }
+}
+
+impl Drop for LoopingNote {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Plays a chord (given as frequencies; see `tuning::freqs`), either all at
+/// once or (arpeggiated) one note at a time from the bottom up.
+fn play_chord(freqs: &[f32], arpeggiate: bool, tone: Tone) {
+    if arpeggiate {
+        let tone = Tone { duration: tone.duration * ARPEGGIO_NOTE_LENGTH,
+                          ..tone };
+        for &freq in freqs {
+            play_freqs(&[freq], tone);
+        }
+    }
+    else {
+        let tone = Tone { duration: tone.duration * CHORD_LENGTH, ..tone };
+        play_freqs(freqs, tone);
     }
 }
 
-fn play_note(note: u32) {
-    let freq = BASE_FREQ * (2.0f32).powf((note as f32 - BASE_NOTE)
-                                         / (NOTES_PER_OCTAVE as f32));
+fn play_note(note: u32, tone: Tone) {
+    if !midi::play(&[note], tone) && !stream::play(&[note_freq(note)], tone) {
+        let _ = start_note(note, tone).wait();
+    }
+}
+
+/// Starts playing a short, percussive metronome click.
+fn start_click() -> Child {
+    Command::new("play").arg("-q").arg("-n")
+        .arg("synth").arg("0.03").arg("whitenoise").arg("highpass").arg("2000")
+        .arg("fade").arg("0").arg("0.03").arg("0.025").arg("vol").arg("0.5")
+        .spawn().unwrap_or_else(|x| sox_failed("play", x))
+}
+
+/// Plays a quick, bright two-note chime, for a right answer.
+fn play_success_sound() {
     let _ = Command::new("play").arg("-q").arg("-n")
-        .arg("synth").arg("1").arg("sine").arg(&format!("{}", freq))
-        .arg("fade").arg("0.1").arg("1").arg("0.7").arg("vol").arg("0.6")
-        .spawn().expect("failed to start playback").wait();
+        .arg("synth").arg("0.25").arg("sine").arg("1046.5")
+        .arg("sine").arg("1318.5").arg("delay").arg("0").arg("0.08")
+        .arg("remix").arg("-")
+        .arg("fade").arg("0").arg("0.25").arg("0.2").arg("vol").arg("0.4")
+        .spawn().unwrap_or_else(|x| sox_failed("play", x)).wait();
 }
 
-fn guess_note(note: u32, note_name: &str, full_note_name: &str, compares: &mut u32, confirms: &mut u32, offness_total: &mut u32) -> Guess {
-    let mut buf = String::new();
-    let stdin = stdin();
-    let mut stdin = stdin.lock();
-    loop {
-        println!("Your guess?");
-        buf.clear();
-        match stdin.read_line(&mut buf) {
-            Ok(_) => (),
-            Err(_) => std::process::exit(0),
+/// Plays a short, low buzz, for running out of guesses.
+fn play_failure_sound() {
+    let _ = Command::new("play").arg("-q").arg("-n")
+        .arg("synth").arg("0.3").arg("square").arg("110")
+        .arg("fade").arg("0").arg("0.3").arg("0.1").arg("vol").arg("0.25")
+        .spawn().unwrap_or_else(|x| sox_failed("play", x)).wait();
+}
+
+/// Says a note name out loud, with `espeak`. `#` and `b` are read as "sharp"
+/// and "flat".
+fn speak(name: &str) {
+    let mut words = String::new();
+    for c in name.chars() {
+        match c {
+            '#' => words.push_str(" sharp"),
+            'b' => words.push_str(" flat"),
+            x if x.is_ascii_digit() && !words.ends_with(|x: char| {
+                x.is_ascii_digit()
+            }) => {
+                words.push(' ');
+                words.push(x);
+            },
+            x => words.push(x),
         }
-        while buf.ends_with("\n") { buf.pop(); }
-        if VALID_NOTE_PATTERN.is_match(&buf) {
-            if buf == full_note_name {
-                return Guess::Perfect
+    }
+    match Command::new("espeak").arg(&words).output() {
+        Ok(_) => (),
+        Err(x) if x.kind() == ErrorKind::NotFound => {
+            eprintln!("Couldn't find `espeak`. Is it installed, and in your \
+                       PATH?");
+        },
+        Err(x) => eprintln!("Couldn't run `espeak`: {}", x),
+    }
+}
+
+/// Plays a sequence of notes. With a metronome, one bar of clicks counts in,
+/// and then each note lands on a beat with a click under it. Without one, the
+/// notes just play one after another.
+///
+/// With `portamento`, the notes glide into each other instead (see
+/// `start_glide`).
+fn play_melody(notes: &[u32], metronome: Option<u32>, portamento: bool,
+               tone: Tone) {
+    let bpm = match metronome {
+        None if portamento => {
+            let _ = start_glide(notes, tone).wait();
+            return
+        },
+        None => {
+            for &note in notes { play_note(note, tone) }
+            return
+        },
+        Some(bpm) => bpm,
+    };
+    let beat = Duration::from_secs(60) / bpm;
+    let start = Instant::now();
+    let mut children = vec![];
+    for beat_index in 0 .. METRONOME_COUNT_IN + notes.len() as u32 {
+        let now = Instant::now();
+        let target = start + beat * beat_index;
+        if target > now { sleep(target - now) }
+        children.push(start_click());
+        let tone = Tone { duration: beat.as_secs_f32(), ..tone };
+        if portamento {
+            if beat_index == METRONOME_COUNT_IN {
+                children.push(start_glide(notes, tone));
             }
-            else if &buf[..buf.len()-1] == note_name {
-                return Guess::WrongOctave
+        }
+        else if let Some(&note) = beat_index.checked_sub(METRONOME_COUNT_IN)
+            .and_then(|index| notes.get(index as usize)) {
+            children.push(start_note(note, tone));
+        }
+    }
+    for mut child in children { let _ = child.wait(); }
+}
+
+/// Wraps the input, remembering the last line read from it, so that
+/// `--blind` can show you your guesses at the end.
+struct Transcript<R> {
+    inner: R,
+    last_line: String,
+}
+
+impl<R: BufRead> Transcript<R> {
+    fn new(inner: R) -> Transcript<R> {
+        Transcript { inner, last_line: String::new() }
+    }
+    fn last_line(&self) -> &str { &self.last_line }
+}
+
+impl<R: BufRead> Read for Transcript<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: BufRead> BufRead for Transcript<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> { self.inner.fill_buf() }
+    fn consume(&mut self, amount: usize) { self.inner.consume(amount) }
+    fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        let start = buf.len();
+        let result = self.inner.read_line(buf)?;
+        self.last_line = buf[start..].trim_end_matches(['\r', '\n'])
+            .to_string();
+        Ok(result)
+    }
+}
+
+/// Opens wherever guesses come from: `--input-fd`, or else the terminal,
+/// and `--midi-in`.
+fn open_input<'a>(invocation: &Invocation, stdin: &'a Stdin)
+                  -> Box<dyn BufRead + 'a> {
+    if let Some(device) = invocation.midi_in.as_ref() {
+        let typed: Box<dyn BufRead + Send> = match invocation.input_fd
+            .as_ref() {
+            Some(path) => match File::open(path) {
+                Ok(x) => Box::new(BufReader::new(x)),
+                Err(x) => {
+                    eprintln!("Couldn't open {:?}: {}", path, x);
+                    std::process::exit(1)
+                },
+            },
+            None => Box::new(BufReader::new(std::io::stdin())),
+        };
+        let transpose = match invocation.transpose_target {
+            TransposeTarget::Written => invocation.transpose,
+            TransposeTarget::Sounding => 0,
+        };
+        match midi::input(device, typed, transpose) {
+            Ok(x) => return Box::new(x),
+            Err(x) => {
+                eprintln!("{}", x);
+                std::process::exit(1)
+            },
+        }
+    }
+    match invocation.input_fd.as_ref() {
+        Some(path) => match File::open(path) {
+            Ok(x) => Box::new(BufReader::new(x)),
+            Err(x) => {
+                eprintln!("Couldn't open {:?}: {}", path, x);
+                std::process::exit(1)
+            },
+        },
+        None => Box::new(stdin.lock()),
+    }
+}
+
+/// Where guesses are read from, and where the prompt for each one goes.
+struct GuessInput<'a> {
+    lines: &'a mut dyn BufRead,
+    prompt: &'a mut dyn Write,
+}
+
+/// Prompts for and reads one line of input. See `read_input`.
+fn read_guess(input: &mut GuessInput) -> Option<String> {
+    verbosity::print_line_to(input.prompt, format_args!("Your guess?"));
+    read_input(input.lines)
+}
+
+/// Reads one line of input, without the line ending. Returns `None` at the
+/// end of input. A read error is fatal.
+fn read_input(input: &mut dyn BufRead) -> Option<String> {
+    let mut buf = String::new();
+    match input.read_line(&mut buf) {
+        Ok(0) => return None,
+        Ok(_) => (),
+        Err(x) => {
+            eprintln!("Error reading input: {}", x);
+            std::process::exit(1)
+        },
+    }
+    while buf.ends_with('\n') || buf.ends_with('\r') { buf.pop(); }
+    Some(buf)
+}
+
+/// How guesses are taken in note mode, the same all session.
+#[derive(Clone,Copy,Default)]
+struct NoteRules {
+    ignore_octave: bool,
+    allow_hedge: bool,
+    loop_note: bool,
+    /// How many times "prev" can be used in a session.
+    prev_limit: Option<u32>,
+}
+
+/// What "p<note>", "?", and "prev" have been used for, over a session.
+#[derive(Clone,Copy,Default,Serialize,Deserialize)]
+struct GuessStats {
+    compares: u32,
+    confirms: u32,
+    offness_total: u32,
+    /// How many times "prev" was used, for `--prev-limit`.
+    #[serde(default)]
+    prevs: u32,
+}
+
+/// With `strict_spelling`, a guess at the right pitch that isn't spelled that
+/// way is `Guess::Misspelled`. `previous` is the note "prev" plays, if any.
+#[allow(clippy::manual_strip, clippy::manual_abs_diff)]
+fn guess_note(input: &mut GuessInput, note: u32,
+              strict_spelling: Option<Spelling>, tone: Tone,
+              previous: Option<u32>, rules: NoteRules,
+              stats: &mut GuessStats) -> Guess {
+    let NoteRules { ignore_octave, allow_hedge, loop_note, prev_limit }
+        = rules;
+    let start_loop = || loop_note.then(|| LoopingNote::start(note, tone));
+    let mut looping = start_loop();
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        if let Some(guess) = parse_guess(&buf, ignore_octave) {
+            match strict_spelling {
+                Some(spelling) if guess.pitch_class == note % NOTES_PER_OCTAVE
+                    && guess.spelling != spelling
+                    => return Guess::Misspelled(spelling),
+                _ => return judge_note(&guess, note, ignore_octave),
             }
-            else {
-                return Guess::Wrong
+        }
+        else if let Some(guesses) = parse_hedge(&buf, ignore_octave)
+            .filter(|_| allow_hedge) {
+            return judge_hedge(&guesses, note, ignore_octave)
+        }
+        else if buf.trim() == "prev" {
+            match previous {
+                None => say!(Essential, "There's no previous note to play."),
+                Some(_) if prev_limit.is_some_and(|x| stats.prevs >= x) => {
+                    say!(Essential, "You've already heard the previous note \
+                                     as many times as you can.");
+                },
+                Some(previous) => {
+                    drop(looping.take());
+                    play_note(previous, tone);
+                    looping = start_loop();
+                    stats.prevs += 1;
+                },
             }
         }
         else if buf.starts_with("p") {
             let to_play = name_to_note(&buf[1..]);
             match to_play {
                 None => {
-                    println!("Invalid note for playback");
+                    say!(Essential, "Invalid note for playback");
                 },
                 Some(to_play) => {
                     let offness = if note > to_play { note - to_play } else { to_play - note };
-                    play_note(to_play);
-                    *compares += 1;
-                    *offness_total += offness;
+                    drop(looping.take());
+                    play_note(to_play, tone);
+                    looping = start_loop();
+                    stats.compares += 1;
+                    stats.offness_total += offness;
                 }
             }
         }
         else if buf == "?" {
-            play_note(note);
-            *confirms += 1;
+            if looping.is_none() { play_note(note, tone) }
+            stats.confirms += 1;
+        }
+        else if buf.trim().is_empty() || buf.trim() == "skip" {
+            return Guess::Skipped
+        }
+        else if ignore_octave {
+            say!(Essential, "Please enter a note name (e.g. \"C#\"), or \"?\" \
+                             to repeat the note\nplayback, or p<note> to \
+                             play a note (cheater!), or \"prev\" to play the \
+                             last round's\nnote, or \"skip\" to give up");
         }
         else {
-            println!("Please enter a note in MIDI notation (e.g. \"C#4\"), or \
-                            \"?\" to repeat the\nnote playback, or p<note> \
-                            to play a note (cheater!)");
+            say!(Essential, "Please enter a note and its octave (e.g. \
+                             {:?}), or \"?\" to repeat the\nnote \
+                             playback, or p<note> to play a note (cheater!), \
+                             or \"prev\" to play\nthe last round's note, or \
+                             \"skip\" to give up", example_note());
         }
     }
 }
 
-fn main() {
-    let invocation = Invocation::parse();
-    let octaves = invocation.octaves.min(5).max(1);
-    let octaves_below = octaves/2;
-    let octaves_above = (octaves+1)/2;
-    let min_note = MIDDLE_C - octaves_below * NOTES_PER_OCTAVE;
-    let max_note = MIDDLE_C + octaves_above * NOTES_PER_OCTAVE;
-    let mut compares = 0;
-    let mut confirms = 0;
-    let mut offness_total = 0;
-    println!(" Lowest note we'll play: {}", full_note_name(min_note));
-    println!("Highest note we'll play: {}", full_note_name(max_note));
-    let mut perfect_count = 0;
-    let mut right_count = 0;
-    let mut rng = thread_rng();
-    for _ in 0 .. invocation.test_count {
-        let note = rng.gen_range(min_note ..= max_note);
-        println!("---");
-        play_note(note);
-        let note_name = note_name(note);
-        let full_note_name = full_note_name(note);
-        for rem_guesses in (0 .. invocation.attempt_limit).rev() {
-            match guess_note(note, &note_name, &full_note_name, &mut compares, &mut confirms, &mut offness_total) {
-                Guess::Wrong => {
-                    if rem_guesses > 1 {
-                        println!("Try again ({} guesses left)", rem_guesses);
-                    }
-                    else if rem_guesses > 0 {
-                        println!("Try again (last guess)");
-                    }
-                    else {
-                        println!("Out of guesses.");
-                        println!("The note was: {}", full_note_name);
-                    }
-                },
-                Guess::WrongOctave => {
-                    println!("You got the note right, but the octave wrong.");
-                    println!("The correct answer was: {}", full_note_name);
-                    right_count += 1;
-                    break
-                },
-                Guess::Perfect => {
-                    println!("Correct!");
-                    perfect_count += 1;
-                    break
-                },
-            }
+fn guess_octave(input: &mut GuessInput, note: u32, octave: i32, tone: Tone)
+                -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match buf.trim().parse::<i32>() {
+            Ok(guess) if guess == octave => return Guess::Perfect,
+            Ok(_) => return Guess::Wrong(None),
+            Err(_) if buf == "?" => play_note(note, tone),
+            Err(_) => {
+                say!(Essential, "Please enter an octave number (e.g. \"3\"), \
+                                 or \"?\" to repeat the note");
+            },
         }
     }
-    println!("You got {}/{} correct. Half credit for {} wrong-octave guesses.",
-             perfect_count, invocation.test_count, right_count);
-    let score = ((perfect_count * 2 + right_count) * 100 / invocation.test_count + 1) / 2;
-    println!("Your final score: {}% = {}, {} compares, {} confirms, offness total {}", score,
-             match score {
-                 x if x >= 100 => "S",
-                 x if x >= 97 => "A+",
-                 x if x >= 94 => "A",
-                 x if x >= 90 => "A-",
-                 x if x >= 87 => "B+",
-                 x if x >= 84 => "B",
-                 x if x >= 80 => "B-",
-                 x if x >= 77 => "C+",
-                 x if x >= 74 => "C",
-                 x if x >= 70 => "C-",
-                 x if x >= 67 => "D+",
-                 x if x >= 64 => "D",
-                 x if x >= 60 => "D-",
-                 _ => "F",
-             }, compares, confirms, offness_total);
+}
+
+/// `freqs` are the notes sounding together, for count-notes mode.
+fn guess_count(input: &mut GuessInput, count: usize, freqs: &[f32],
+               tone: Tone) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match buf.trim().parse::<usize>() {
+            Ok(guess) if guess == count => return Guess::Perfect,
+            Ok(_) => return Guess::Wrong(None),
+            Err(_) if buf == "?" => play_chord(freqs, false, tone),
+            Err(_) => {
+                say!(Essential, "Please enter how many notes you heard (e.g. \
+                                 \"3\"), or \"?\" to hear them again");
+            },
+        }
+    }
+}
+
+fn guess_interval(input: &mut GuessInput, interval: u32, low_name: &str, high_name: &str) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_interval(&buf) {
+            Some(guess) if guess == interval => return Guess::Perfect,
+            Some(_) => return Guess::Wrong(None),
+            None if buf == "?" => {
+                say!(Essential, "{} {}", low_name, high_name);
+            },
+            None => {
+                say!(Essential, "Please enter an interval (e.g. \"m3\", \
+                                 \"P5\", \"TT\"), or \"?\" to see the \
+                                 notes again");
+            },
+        }
+    }
+}
+
+/// Like `guess_interval`, but the interval is heard, not seen.
+fn guess_played_interval(input: &mut GuessInput, notes: &[u32], tone: Tone)
+                         -> Guess {
+    let interval = notes[0].abs_diff(notes[1]);
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_interval(&buf) {
+            Some(guess) if guess == interval => return Guess::Perfect,
+            Some(_) => return Guess::Wrong(None),
+            None if buf == "?" => play_melody(notes, None, false, tone),
+            None => {
+                say!(Essential, "Please enter an interval (e.g. \"m3\", \
+                                 \"P5\", \"TT\"), or \"?\" to hear the \
+                                 interval again");
+            },
+        }
+    }
+}
+
+fn guess_interval_quality(input: &mut GuessInput, notes: &[u32], tone: Tone)
+                          -> Guess {
+    let quality = interval_quality(notes[0].abs_diff(notes[1]));
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_interval_quality(&buf) {
+            Some(guess) if guess == quality => return Guess::Perfect,
+            Some(_) => return Guess::Wrong(None),
+            None if buf == "?" => play_melody(notes, None, false, tone),
+            None => {
+                say!(Essential, "Please enter \"perfect\", \"major\", \
+                                 \"minor\", or \"tritone\", or \"?\" to \
+                                 hear the interval again");
+            },
+        }
+    }
+}
+
+fn guess_melody(input: &mut GuessInput, notes: &[u32], ignore_octave: bool,
+                metronome: Option<u32>, portamento: bool, tone: Tone)
+                -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        if buf == "?" {
+            play_melody(notes, metronome, portamento, tone);
+            continue
+        }
+        match parse_melody(&buf, ignore_octave) {
+            Some(guesses) if guesses.len() == notes.len() => {
+                return judge_melody(&guesses, notes, ignore_octave)
+            },
+            _ => {
+                let c4 = MIDDLE_C + NOTES_PER_OCTAVE;
+                let example: Vec<String> = [c4, c4 + 4, c4 + 7, c4 + 12]
+                    .iter().map(|&x| full_note_name(x)).collect();
+                say!(Essential, "Please enter all {} notes of the melody, \
+                                 separated by spaces (e.g.\n\"{}\" or \
+                                 \"{}\"), or \"?\" to repeat the melody",
+                     notes.len(), example.join(" "), example.concat());
+            },
+        }
+    }
+}
+
+fn guess_inversion(input: &mut GuessInput, inversion: u32, freqs: &[f32],
+                   arpeggiate: bool, tone: Tone) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_inversion(&buf) {
+            Some(guess) if guess == inversion => return Guess::Perfect,
+            Some(_) => return Guess::Wrong(None),
+            None if buf == "?" => play_chord(freqs, arpeggiate, tone),
+            None => {
+                say!(Essential, "Please enter \"root\", \"1st\", or \"2nd\", \
+                                 or \"?\" to hear the chord again");
+            },
+        }
+    }
+}
+
+fn guess_chord_quality(input: &mut GuessInput, quality: ChordQuality,
+                       freqs: &[f32], arpeggiate: bool, tone: Tone) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_chord_quality(&buf) {
+            Some(guess) if guess == quality => return Guess::Perfect,
+            Some(_) => return Guess::Wrong(None),
+            None if buf == "?" => play_chord(freqs, arpeggiate, tone),
+            None => {
+                say!(Essential, "Please enter \"maj\", \"min\", \"dim\", \
+                                 \"aug\", \"dom7\", \"maj7\", or \"min7\", or \
+                                 \"?\" to hear the chord again");
+            },
+        }
+    }
+}
+
+/// Parses a guess at a frequency, like `440`, `440Hz`, or `1.2kHz`.
+fn parse_frequency(guess: &str) -> Option<f32> {
+    let guess = guess.trim().to_ascii_lowercase();
+    let (number, scale) = match guess.strip_suffix("khz") {
+        Some(x) => (x, 1000.0),
+        None => (guess.strip_suffix("hz").unwrap_or(&guess), 1.0),
+    };
+    let freq: f32 = number.trim().parse().ok()?;
+    (freq.is_finite() && freq > 0.0).then_some(freq * scale)
+}
+
+/// A guess at a frequency is right if it's within `tolerance` percent.
+fn judge_frequency(guess: f32, freq: f32, tolerance: f32) -> Guess {
+    if ((guess - freq) / freq * 100.0).abs() <= tolerance { Guess::Perfect }
+    else { Guess::Wrong(None) }
+}
+
+/// `freq` is what's actually played, which is what has to be guessed.
+fn guess_frequency(input: &mut GuessInput, note: u32, freq: f32,
+                   tolerance: f32, tone: Tone) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_frequency(&buf) {
+            Some(guess) => {
+                let off = (guess - freq) / freq * 100.0;
+                let nearest = nearest_note(guess)
+                    .map_or_else(String::new,
+                                 |x| format!(" (near {})", full_note_name(x)));
+                say!(Essential, "{:.1}Hz{} is {:.1}% too {}.", guess,
+                     nearest, off.abs(), if off > 0.0 { "high" } else { "low" });
+                let result = judge_frequency(guess, freq, tolerance);
+                if matches!(result, Guess::Perfect) {
+                    say!(Essential, "It was {:.1}Hz ({}).", freq,
+                         full_note_name(note));
+                }
+                return result
+            },
+            None if buf == "?" => play_note(note, tone),
+            None => {
+                say!(Essential, "Please enter a frequency (e.g. \"440\" or \
+                                 \"1.2kHz\"), or \"?\" to repeat the note");
+            },
+        }
+    }
+}
+
+/// A guess at how out of tune a note is: which way (`1` for sharp, `-1` for
+/// flat, `0` for in tune), and maybe by how many cents.
+struct DetuneGuess { direction: i32, cents: Option<f32> }
+
+/// Parses a guess like `sharp`, `flat 20`, `in tune`, or `+15`.
+fn parse_detune(guess: &str) -> Option<DetuneGuess> {
+    let guess = guess.trim().to_ascii_lowercase();
+    if let Ok(cents) = guess.trim_start_matches('+').parse::<f32>() {
+        if !cents.is_finite() { return None }
+        let direction = if cents > 0.0 { 1 } else if cents < 0.0 { -1 }
+        else { 0 };
+        return Some(DetuneGuess { direction, cents: Some(cents.abs()) })
+    }
+    let (word, cents) = match guess.rsplit_once(' ') {
+        Some((word, cents)) if cents.parse::<f32>().is_ok() => {
+            (word.trim(), cents.parse::<f32>().ok())
+        },
+        _ => (guess.as_str(), None),
+    };
+    if cents.is_some_and(|x| !x.is_finite() || x < 0.0) { return None }
+    let direction = match word {
+        "sharp" | "s" | "#" | "high" => 1,
+        "flat" | "f" | "b" | "low" => -1,
+        "in tune" | "in" | "t" | "0" => 0,
+        _ => return None,
+    };
+    Some(DetuneGuess { direction, cents })
+}
+
+/// A guess at a detuned note is right if it's the right way out of tune,
+/// and, if it says how far, within `tolerance` cents of that.
+fn judge_detune(guess: &DetuneGuess, detune: i32, tolerance: f32) -> Guess {
+    if guess.direction != detune.signum() { return Guess::Wrong(None) }
+    match guess.cents {
+        Some(cents) if detune != 0
+            && (cents - detune.unsigned_abs() as f32).abs() > tolerance
+            => Guess::Wrong(None),
+        _ => Guess::Perfect,
+    }
+}
+
+fn detune_name(detune: i32) -> String {
+    match detune.signum() {
+        0 => "in tune".to_string(),
+        1 => format!("{} cents sharp", detune),
+        _ => format!("{} cents flat", -detune),
+    }
+}
+
+fn guess_detune(input: &mut GuessInput, freq: f32, detune: i32,
+                tolerance: f32, tone: Tone) -> Guess {
+    loop {
+        let buf = match read_guess(input) {
+            Some(buf) => buf,
+            None => return Guess::Quit,
+        };
+        match parse_detune(&buf) {
+            Some(guess) => {
+                let result = judge_detune(&guess, detune, tolerance);
+                if guess.direction == detune.signum()
+                    && matches!(result, Guess::Wrong(_)) {
+                    say!(Essential, "That's the right way, but not the right \
+                                     amount.");
+                }
+                return result
+            },
+            None if buf == "?" => play_freqs(&[freq], tone),
+            None => {
+                say!(Essential, "Please enter \"sharp\", \"flat\", or \"in \
+                                 tune\" (optionally followed by how many \
+                                 cents), or a number of cents (e.g. \"+20\" \
+                                 or \"-15\"), or \"?\" to repeat the note");
+            },
+        }
+    }
+}
+
+/// With `record_to`, each recording is saved there as a WAV file.
+fn guess_sing(input: &mut GuessInput, note: u32, tolerance: f32,
+              record_to: Option<&Path>) -> Guess {
+    let target = note_freq(note);
+    loop {
+        say!(Essential, "Press enter, then sing {} for {} seconds.",
+             full_note_name(note), SING_DURATION);
+        if read_input(input.lines).is_none() { return Guess::Quit }
+        say!(Essential, "Listening...");
+        let samples = pitch::record(SING_DURATION);
+        match record_to {
+            Some(path) if samples.is_empty() => {
+                eprintln!("Nothing was recorded, so {:?} wasn't saved.", path);
+            },
+            Some(path) => if let Err(x) = pitch::write_wav(path, &samples) {
+                eprintln!("Couldn't save {:?}: {}", path, x);
+            },
+            None => (),
+        }
+        let freq = match pitch::detect_pitch(&samples) {
+            Some(freq) => freq,
+            None => {
+                say!(Essential, "I couldn't hear a clear pitch. Let's try \
+                                 that again.");
+                continue
+            },
+        };
+        let cents = pitch::cents_off(freq, target);
+        // how far off we are from the nearest octave of the target
+        let octave_cents = cents - (cents / 1200.0).round() * 1200.0;
+        say!(Essential, "You sang {:.1}Hz, which is {:+.0} cents from {}.",
+             freq, cents, full_note_name(note));
+        if cents.abs() <= tolerance { return Guess::Perfect }
+        else if octave_cents.abs() <= tolerance {
+            return Guess::WrongOctave(None)
+        }
+        else { return Guess::Wrong(None) }
+    }
+}
+
+/// The running tallies of a session. (This is what `--resume` picks back up.)
+#[derive(Clone,Default,Serialize,Deserialize)]
+struct Session {
+    rounds_played: u32,
+    perfect_count: u32,
+    right_count: u32,
+    hedged_count: u32,
+    #[serde(default)]
+    skipped_count: u32,
+    #[serde(flatten)]
+    guess_stats: GuessStats,
+    breakdown: [PitchClassStats; NOTES_PER_OCTAVE as usize],
+    confusion: ConfusionMatrix,
+    boosts: Vec<Boost>,
+    round_records: Vec<history::RoundRecord>,
+    /// For `--blind`: the answer, what you guessed, and how that went, for
+    /// each round.
+    answer_key: Vec<(String, String, String)>,
+    /// For `--must-include`: which rounds play which notes, as (round,
+    /// note).
+    #[serde(default)]
+    scheduled: Vec<(u32, u32)>,
+    /// For `--lives`.
+    #[serde(default)]
+    lives_lost: u32,
+    /// How many octaves off each wrong-octave answer was, for
+    /// `--octave-distance-credit`.
+    #[serde(default)]
+    octave_distances: Vec<u32>,
+    /// In melody mode, how many notes were in the right place in the best
+    /// guess at each melody that was missed.
+    #[serde(default)]
+    melody_credit: u32,
+}
+
+impl Session {
+    fn score(&self, weights: Weights, distance_credit: bool,
+             melody_length: usize) -> u32 {
+        if self.melody_credit > 0 && !distance_credit {
+            compute_melody_score(weights, self.perfect_count,
+                                 self.right_count, self.hedged_count,
+                                 self.melody_credit, melody_length,
+                                 self.rounds_played)
+        }
+        else if distance_credit {
+            compute_distance_score(weights, self.perfect_count,
+                                   &self.octave_distances, self.hedged_count,
+                                   self.rounds_played)
+        }
+        else {
+            compute_score(weights, self.perfect_count, self.right_count,
+                          self.hedged_count, self.rounds_played)
+        }
+    }
+}
+
+/// A note that's temporarily more likely to be picked, for
+/// `--neighbor-drill`.
+#[derive(Clone,Serialize,Deserialize)]
+struct Boost {
+    note: u32,
+    rounds_left: u32,
+}
+
+/// What gets tested in one round.
+struct Round {
+    /// Every note that gets played (or shown), in order.
+    notes: Vec<u32>,
+    /// In inversion mode, the chord those notes make up.
+    chord: Option<Chord>,
+}
+
+/// Picks what to test in one round. Boosted notes are only considered in the
+/// single-note modes. With `spellings` (see `--from-scale`), only notes in
+/// those pitch classes are picked, except in the interval and inversion
+/// modes. In the interval modes, `shape.fixed_root` (see `--fixed-root`) is
+/// always the first note.
+#[allow(clippy::too_many_arguments)]
+fn draw_round(mode: Mode, rng: &mut impl Rng, min_note: u32, max_note: u32,
+              boosts: &[Boost], spellings: Option<&[Spelling]>,
+              note_weights: Option<&NoteWeights>, shape: RoundShape)
+              -> Round {
+    let RoundShape { fixed_root, direction, max_cluster, melody_length }
+        = shape;
+    if mode == Mode::Inversion {
+        let quality = INVERSION_QUALITIES[rng.gen_range(
+            0 .. INVERSION_QUALITIES.len())];
+        let highest_root = max_note.saturating_sub(Chord::max_span(quality))
+            .max(min_note);
+        let chord = Chord { root: rng.gen_range(min_note ..= highest_root),
+                            quality,
+                            inversion: rng.gen_range(0 .. 3) };
+        return Round { notes: chord.notes(), chord: Some(chord) }
+    }
+    if mode == Mode::ChordQuality {
+        let quality = CHORD_QUALITIES[rng.gen_range(
+            0 .. CHORD_QUALITIES.len())];
+        let span = *quality.intervals().last().unwrap();
+        let highest_root = max_note.saturating_sub(span).max(min_note);
+        let chord = Chord { root: rng.gen_range(min_note ..= highest_root),
+                            quality, inversion: 0 };
+        return Round { notes: chord.notes(), chord: Some(chord) }
+    }
+    let allowed = |note: u32| spellings.is_none_or(|spellings| {
+        spellings.iter().any(|x| x.pitch_class() == note % NOTES_PER_OCTAVE)
+    });
+    let notes = match mode {
+        Mode::Note | Mode::Octave | Mode::Sing | Mode::Duel | Mode::Learn
+            | Mode::Frequency | Mode::Detune | Mode::Mixed
+            | Mode::Melody
+            if !boosts.is_empty() || note_weights.is_some() => {
+            let weights = (min_note ..= max_note).map(|note| {
+                let weight = note_weights.map_or(1.0, |x| x.weight(note));
+                if !allowed(note) { 0.0 }
+                else if boosts.iter().any(|boost| boost.note == note) {
+                    weight * NEIGHBOR_DRILL_WEIGHT as f32
+                } else { weight }
+            });
+            let index = WeightedIndex::new(weights).unwrap();
+            let count = if mode == Mode::Melody { melody_length } else { 1 };
+            (0 .. count).map(|_| min_note + index.sample(rng) as u32)
+                .collect()
+        },
+        Mode::Note | Mode::Octave | Mode::Sing | Mode::Duel | Mode::Learn
+            | Mode::Frequency | Mode::Detune | Mode::Mixed
+            | Mode::Melody
+            if spellings.is_some() => {
+            let candidates: Vec<u32> = (min_note ..= max_note)
+                .filter(|&note| allowed(note)).collect();
+            let count = if mode == Mode::Melody { melody_length } else { 1 };
+            (0 .. count)
+                .map(|_| candidates[rng.gen_range(0 .. candidates.len())])
+                .collect()
+        },
+        Mode::Note | Mode::Octave | Mode::Sing | Mode::Duel | Mode::Learn
+            | Mode::Frequency | Mode::Detune | Mode::Mixed
+            => vec![rng.gen_range(min_note ..= max_note)],
+        Mode::Interval | Mode::TheoryInterval | Mode::IntervalQuality
+            => match fixed_root {
+            Some(root) => {
+                let lowest = if direction == IntervalDirection::Up { root }
+                else { root.saturating_sub(MAX_INTERVAL).max(min_note) };
+                let highest = if direction == IntervalDirection::Down { root }
+                else { (root + MAX_INTERVAL).min(max_note) };
+                vec![root, rng.gen_range(lowest ..= highest)]
+            },
+            None => {
+                let interval = rng.gen_range(0 ..= MAX_INTERVAL
+                                             .min(max_note - min_note));
+                let low = rng.gen_range(min_note ..= max_note - interval);
+                let up = match direction {
+                    IntervalDirection::Both => rng.gen(),
+                    IntervalDirection::Up => true,
+                    IntervalDirection::Down => false,
+                };
+                if up { vec![low, low + interval] }
+                else { vec![low + interval, low] }
+            },
+        },
+        Mode::Melody => (0 .. melody_length)
+            .map(|_| rng.gen_range(min_note ..= max_note)).collect(),
+        Mode::CountNotes => {
+            let range = (max_note - min_note + 1) as usize;
+            let count = rng.gen_range(2 ..= max_cluster).min(range);
+            let mut notes: Vec<u32> = sample(rng, range, count).iter()
+                .map(|x| min_note + x as u32).collect();
+            notes.sort_unstable();
+            notes
+        },
+        Mode::Inversion | Mode::ChordQuality | Mode::Progression
+            => unreachable!(),
+    };
+    Round { notes, chord: None }
+}
+
+/// How many different intervals `draw_round` can pick in the interval modes.
+fn interval_pool(min_note: u32, max_note: u32, fixed_root: Option<u32>,
+                 direction: IntervalDirection) -> usize {
+    match fixed_root {
+        Some(root) => {
+            let above = (max_note - root).min(MAX_INTERVAL);
+            let below = (root - min_note).min(MAX_INTERVAL);
+            match direction {
+                IntervalDirection::Up => above as usize + 1,
+                IntervalDirection::Down => below as usize + 1,
+                IntervalDirection::Both => above.max(below) as usize + 1,
+            }
+        },
+        None => MAX_INTERVAL.min(max_note - min_note) as usize + 1,
+    }
+}
+
+/// Scores an answer sheet, one answer per line and one line per round,
+/// against the notes a session with this seed would have played. Prints only
+/// the final grade.
+fn run_assessment(invocation: &Invocation, path: &Path, rng: &mut impl Rng,
+                  min_note: u32, max_note: u32,
+                  spellings: Option<&[Spelling]>,
+                  note_weights: Option<&NoteWeights>) {
+    let answers = match std::fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Couldn't read {:?}: {}", path, x);
+            std::process::exit(1)
+        },
+    };
+    let answers: Vec<&str> = answers.lines().collect();
+    if answers.len() != invocation.test_count as usize {
+        eprintln!("The answer file has {} answers, but there are {} notes to \
+                   test. (Use -t to change the number of notes.)",
+                  answers.len(), invocation.test_count);
+        std::process::exit(1)
+    }
+    match assess(invocation, &answers, rng, min_note, max_note, spellings,
+                 note_weights) {
+        Some(score) => {
+            say!(Essential, "Your final score: {}% = {}", score,
+                 grade_for(score));
+        },
+        None => say!(Essential, "No rounds completed."),
+    }
+}
+
+/// Grades `answers`, one per round, against the rounds `rng` draws, and
+/// returns the score, or `None` if there were no rounds. The tally is only
+/// printed with `--verbose`.
+fn assess(invocation: &Invocation, answers: &[&str], rng: &mut impl Rng,
+          min_note: u32, max_note: u32, spellings: Option<&[Spelling]>,
+          note_weights: Option<&NoteWeights>) -> Option<u32> {
+    if answers.is_empty() { return None }
+    let rounds = answers.len() as u32;
+    let ignore_octave = invocation.ignore_octave;
+    let mut perfect_count = 0;
+    let mut right_count = 0;
+    let mut hedged_count = 0;
+    let mut melody_credit = 0;
+    for answer in answers {
+        let answer = answer.trim();
+        let round = draw_round(invocation.mode, rng, min_note, max_note, &[],
+                               spellings, note_weights,
+                               invocation.round_shape());
+        let notes = round.notes;
+        let guess = match invocation.mode {
+            Mode::Note if invocation.allow_hedge
+                => parse_hedge(answer, ignore_octave)
+                .map(|guesses| judge_hedge(&guesses, notes[0], ignore_octave)),
+            Mode::Note => parse_guess(answer, ignore_octave)
+                .map(|guess| judge_note(&guess, notes[0], ignore_octave)),
+            Mode::Octave => answer.parse::<i32>().ok()
+                .map(|guess| if guess == octave_of(notes[0], spelling_in(
+                    spellings, notes[0])) {
+                    Guess::Perfect
+                } else { Guess::Wrong(None) }),
+            Mode::Interval | Mode::TheoryInterval => parse_interval(answer)
+                .map(|guess| if guess == notes[0].abs_diff(notes[1]) {
+                    Guess::Perfect
+                } else { Guess::Wrong(None) }),
+            Mode::IntervalQuality => parse_interval_quality(answer)
+                .map(|guess| if guess == interval_quality(notes[0]
+                                                          .abs_diff(notes[1])) {
+                    Guess::Perfect
+                } else { Guess::Wrong(None) }),
+            Mode::Melody => parse_melody(answer, ignore_octave)
+                .map(|guess| judge_melody(&guess, &notes, ignore_octave)),
+            Mode::Frequency => parse_frequency(answer)
+                .map(|guess| judge_frequency(guess, note_freq(notes[0]),
+                                             invocation.freq_tolerance)),
+            Mode::Inversion => parse_inversion(answer)
+                .map(|guess| if Some(guess) == round.chord
+                     .map(|chord| chord.inversion) {
+                    Guess::Perfect
+                } else { Guess::Wrong(None) }),
+            Mode::ChordQuality => parse_chord_quality(answer)
+                .map(|guess| if Some(guess) == round.chord
+                     .map(|chord| chord.quality) {
+                    Guess::Perfect
+                } else { Guess::Wrong(None) }),
+            Mode::CountNotes => answer.parse::<usize>().ok()
+                .map(|guess| if guess == notes.len() { Guess::Perfect }
+                     else { Guess::Wrong(None) }),
+            Mode::Sing | Mode::Duel | Mode::Learn | Mode::Progression
+                | Mode::Detune | Mode::Mixed => unreachable!(),
+        };
+        match guess {
+            Some(Guess::Perfect) => perfect_count += 1,
+            Some(Guess::WrongOctave(_)) => right_count += 1,
+            Some(Guess::Hedged) => hedged_count += 1,
+            _ if invocation.mode == Mode::Melody => {
+                melody_credit += parse_melody(answer, ignore_octave)
+                    .map_or(0, |guesses| melody_notes_right(&guesses, &notes,
+                                                            ignore_octave));
+            },
+            _ => (),
+        }
+    }
+    if verbosity::shows(verbosity::Level::Verbose) {
+        print_tally(invocation.weights(), perfect_count, right_count,
+                    hedged_count, rounds);
+        print_melody_credit(melody_credit, invocation.melody_length);
+    }
+    Some(compute_melody_score(invocation.weights(), perfect_count,
+                              right_count, hedged_count, melody_credit,
+                              invocation.melody_length, rounds))
+}
+
+/// For `--smart-attempts`: why the notes in a round deserve extra guesses, one
+/// reason per extra guess.
+fn extra_attempt_reasons(notes: &[u32]) -> Vec<&'static str> {
+    let mut reasons = vec![];
+    if notes.iter()
+        .any(|&note| NOTE_NAMES[(note % NOTES_PER_OCTAVE) as usize].len() > 1) {
+        reasons.push("+1 for the accidental");
+    }
+    if notes.iter().any(|note| !(SMART_LOW_NOTE ..= SMART_HIGH_NOTE)
+                        .contains(note)) {
+        reasons.push("+1 for the extreme octave");
+    }
+    reasons
+}
+
+/// Prints every note that could come up, with the frequency it plays at, for
+/// `--print-notes`. In just intonation, the notes are tuned from the tonic
+/// (see `--tonic`).
+fn print_notes(min_note: u32, max_note: u32, spellings: Option<&[Spelling]>,
+               tone: Tone) {
+    let notes: Vec<u32> = (min_note ..= max_note).filter(|&note| {
+        spellings.is_none() || spelling_in(spellings, note).is_some()
+    }).collect();
+    for (note, freq) in notes.iter().map(|&note| (note, note_freq(note))) {
+        let name = match spelling_in(spellings, note) {
+            Some(x) => x.full_name(note),
+            None => full_note_name(note),
+        };
+        say!(Essential, "{:<5} {:>9.2}Hz", name, tone.sounding(freq));
+    }
+}
+
+/// Writes a numbered worksheet for a session with this seed to `path`, to be
+/// filled in on paper, and its answer key to the same name with `-key` added.
+fn write_worksheet(invocation: &Invocation, path: &Path, rng: &mut impl Rng,
+                   min_note: u32, max_note: u32,
+                   spellings: Option<&[Spelling]>,
+                   note_weights: Option<&NoteWeights>) {
+    let name_of = |note: u32| match spelling_in(spellings, note) {
+        Some(x) if invocation.ignore_octave => x.name(),
+        Some(x) => x.full_name(note),
+        None if invocation.ignore_octave => note_name(note),
+        None => full_note_name(note),
+    };
+    let mode = invocation.mode.to_possible_value().unwrap().get_name();
+    let heading = format!("{} rounds of {} mode, seed {}",
+                          invocation.test_count, mode,
+                          invocation.seed.unwrap());
+    let mut sheet = format!("Worksheet: {}\n\n", heading);
+    let mut key = format!("Answer key: {}\n\n", heading);
+    for index in 1 ..= invocation.test_count {
+        let round = draw_round(invocation.mode, rng, min_note, max_note, &[],
+                               spellings, note_weights,
+                               invocation.round_shape());
+        let notes = round.notes;
+        let names: Vec<String> = notes.iter().map(|&x| name_of(x)).collect();
+        let (question, answer) = match invocation.mode {
+            Mode::Octave => {
+                let spelling = spelling_in(spellings, notes[0]);
+                (format!("{}, octave ", spelling.map_or_else(
+                    || note_name(notes[0]), |x| x.name())),
+                 format!("{}", octave_of(notes[0], spelling)))
+            },
+            Mode::TheoryInterval => {
+                (format!("{} {}: ", full_note_name(notes[0]),
+                         full_note_name(notes[1])),
+                 interval_name(notes[0].abs_diff(notes[1])).to_string())
+            },
+            Mode::Interval => {
+                let interval = notes[0].abs_diff(notes[1]);
+                (String::new(), format!("{} ({})", interval_name(interval),
+                                        names.join(" ")))
+            },
+            Mode::IntervalQuality => {
+                let interval = notes[0].abs_diff(notes[1]);
+                (String::new(), format!("{} ({})", interval_quality(interval),
+                                        interval_name(interval)))
+            },
+            Mode::Inversion => {
+                let chord = round.chord.unwrap();
+                (String::new(), format!("{} ({})",
+                                        inversion_name(chord.inversion),
+                                        chord.name()))
+            },
+            Mode::ChordQuality => {
+                let chord = round.chord.unwrap();
+                (String::new(), format!("{} ({})",
+                                        chord.quality.abbreviation(),
+                                        chord.name()))
+            },
+            Mode::Note | Mode::Melody | Mode::Duel
+                => (String::new(), names.join(" ")),
+            Mode::Frequency => (String::new(),
+                                format!("{:.1}Hz", note_freq(notes[0]))),
+            Mode::CountNotes => (String::new(),
+                                 format!("{} ({})", notes.len(),
+                                         names.join(" "))),
+            Mode::Sing | Mode::Learn | Mode::Progression | Mode::Detune
+                | Mode::Mixed => unreachable!(),
+        };
+        sheet += &format!("{:>3}. {}{}\n", index, question, "_".repeat(20));
+        key += &format!("{:>3}. {}\n", index, answer);
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let key_path = match path.extension() {
+        Some(x) => path.with_file_name(format!("{}-key.{}", stem,
+                                               x.to_string_lossy())),
+        None => path.with_file_name(format!("{}-key", stem)),
+    };
+    for (path, text) in [(path, sheet), (key_path.as_path(), key)] {
+        if let Err(x) = std::fs::write(path, text) {
+            eprintln!("Couldn't write {:?}: {}", path, x);
+            std::process::exit(1)
+        }
+    }
+    say!(Normal, "Wrote the worksheet to {:?} and its answer key to {:?}.",
+         path, key_path);
+}
+
+/// Runs a learn-mode session: plays each note and tells you what it was,
+/// with `--gap` seconds to take it in before the next. Nothing is scored.
+fn run_learn(invocation: &Invocation, rng: &mut impl Rng, min_note: u32,
+             max_note: u32, spellings: Option<&[Spelling]>,
+             note_weights: Option<&NoteWeights>, tone: Tone) {
+    for round in 0 .. invocation.test_count {
+        let note = draw_round(Mode::Learn, rng, min_note, max_note, &[],
+                              spellings, note_weights, PLAIN_ROUND)
+            .notes[0];
+        let name = match spelling_in(spellings, note) {
+            Some(x) => x.full_name(note),
+            None => full_note_name(note),
+        };
+        if invocation.no_progress { say!(Normal, "---") }
+        else {
+            say!(Normal, "--- Note {}/{} {}", round + 1, invocation.test_count,
+                 progress_bar(round, invocation.test_count));
+        }
+        play_note(note, tone);
+        say!(Essential, "That was {}.", name);
+        if invocation.speak { speak(&name) }
+        sleep(Duration::from_secs_f32(invocation.gap));
+    }
+}
+
+/// Runs a progression-mode session: each round plays a few chords, with
+/// `--gap` seconds between them, and takes one guess at their roman numerals.
+/// Each chord named right counts as a right answer.
+fn run_progression(invocation: &Invocation, rng: &mut impl Rng,
+                   min_note: u32, max_note: u32,
+                   scale: Option<&[Spelling]>, tone: Tone) {
+    let stdin = stdin();
+    let mut stdout = stdout();
+    let mut input = GuessInput { lines: &mut open_input(invocation, &stdin),
+                                 prompt: &mut stdout };
+    let (mut right_count, mut chord_count) = (0, 0);
+    'session: for round in 0 .. invocation.test_count {
+        if invocation.no_progress { say!(Normal, "---") }
+        else {
+            say!(Normal, "--- Round {}/{} {}", round + 1,
+                 invocation.test_count,
+                 progress_bar(round, invocation.test_count));
+        }
+        let (scale, key) = match scale {
+            Some(scale) => (scale.to_vec(),
+                            invocation.from_scale.clone().unwrap()),
+            None => {
+                let (letter, accidental) = progression::MAJOR_KEYS[
+                    rng.gen_range(0 .. progression::MAJOR_KEYS.len())];
+                let tonic = Spelling { letter, accidental };
+                (scale::build_scale(tonic, "major").unwrap(),
+                 format!("{} major", tonic.name()))
+            },
+        };
+        let degrees = progression::PROGRESSIONS[
+            rng.gen_range(0 .. progression::PROGRESSIONS.len())];
+        let tonic_class = scale[0].pitch_class();
+        let tonic = (min_note ..= max_note)
+            .find(|note| note % NOTES_PER_OCTAVE == tonic_class)
+            .unwrap_or(min_note + (tonic_class + NOTES_PER_OCTAVE
+                                   - min_note % NOTES_PER_OCTAVE)
+                       % NOTES_PER_OCTAVE);
+        let chords: Vec<Vec<f32>> = degrees.iter().map(|&degree| {
+            let notes = progression::triad_notes(
+                tonic, &progression::triad(&scale, degree));
+            freqs(&notes, tonic, invocation.temperament)
+        }).collect();
+        let play = || for (index, chord) in chords.iter().enumerate() {
+            if index > 0 { sleep(Duration::from_secs_f32(invocation.gap)) }
+            play_chord(chord, invocation.arpeggiate, tone);
+        };
+        say!(Essential, "{} chords in {}:", degrees.len(), key);
+        play();
+        let guess = loop {
+            let Some(buf) = read_guess(&mut input) else { break 'session };
+            if buf == "?" { play() }
+            else if buf.trim().is_empty() || buf.trim() == "skip" {
+                break vec![]
+            }
+            else {
+                match progression::parse_progression(&buf) {
+                    Some(guess) if guess.len() == degrees.len() => break guess,
+                    Some(_) => {
+                        say!(Essential, "There are {} chords to name.",
+                             degrees.len());
+                    },
+                    None => {
+                        say!(Essential, "Please enter a roman numeral for \
+                                         each chord (e.g. \"I vi IV V\"), or \
+                                         \"?\" to hear them again, or \
+                                         \"skip\" to give up");
+                    },
+                }
+            }
+        };
+        let right = guess.iter().zip(degrees.iter())
+            .filter(|(a, b)| a == b).count() as u32;
+        right_count += right;
+        chord_count += degrees.len() as u32;
+        let numerals: Vec<String> = degrees.iter()
+            .map(|&degree| progression::numeral(&scale, degree)).collect();
+        let symbols: Vec<String> = degrees.iter().map(|&degree| {
+            progression::symbol(&progression::triad(&scale, degree))
+        }).collect();
+        if right as usize == degrees.len() { say!(Essential, "Correct!") }
+        else {
+            say!(Essential, "You got {} of {} chords. The progression was: {} \
+                             ({})", right, degrees.len(), numerals.join(" "),
+                 symbols.join(" "));
+        }
+    }
+    if chord_count == 0 {
+        say!(Essential, "No rounds completed.");
+        return
+    }
+    let score = compute_score(invocation.weights(), right_count, 0, 0,
+                              chord_count);
+    say!(Essential, "You named {}/{} chords right.", right_count,
+         chord_count);
+    say!(Essential, "Your final score: {}% = {}", score, grade_for(score));
+}
+
+/// One player's tally, in duel mode.
+struct Player {
+    name: String,
+    perfect_count: u32,
+    right_count: u32,
+}
+
+/// Runs a duel: each round, every player in turn hears the same note and
+/// guesses at it. The answer is only revealed once everyone's had a go.
+/// Scores are kept for each player, and compared at the end.
+fn run_duel(invocation: &Invocation, rng: &mut impl Rng, min_note: u32,
+            max_note: u32, spellings: Option<&[Spelling]>,
+            note_weights: Option<&NoteWeights>, tone: Tone) {
+    let weights = invocation.weights();
+    let mut players: Vec<Player> = invocation.players.split(',')
+        .map(|name| Player { name: name.trim().to_string(),
+                             perfect_count: 0, right_count: 0 })
+        .collect();
+    if players.len() < 2 || players.iter().any(|x| x.name.is_empty()) {
+        eprintln!("A duel needs at least two players, with names separated \
+                   by commas (e.g. \"Alice,Bob\").");
+        std::process::exit(1)
+    }
+    let stdin = stdin();
+    let mut stdout = stdout();
+    let mut input = GuessInput { lines: &mut open_input(invocation, &stdin),
+                                 prompt: &mut stdout };
+    let mut stats = GuessStats::default();
+    let rules = NoteRules { ignore_octave: invocation.ignore_octave,
+                            loop_note: invocation.loop_note,
+                            ..NoteRules::default() };
+    let mut rounds_played = 0;
+    'session: for round in 0 .. invocation.test_count {
+        if invocation.no_progress { say!(Normal, "---") }
+        else {
+            say!(Normal, "--- Round {}/{} {}", round + 1,
+                 invocation.test_count,
+                 progress_bar(round, invocation.test_count));
+        }
+        let note = draw_round(Mode::Duel, rng, min_note, max_note, &[],
+                              spellings, note_weights, PLAIN_ROUND)
+            .notes[0];
+        for player in players.iter_mut() {
+            say!(Essential, "{}, it's your turn.", player.name);
+            if !invocation.loop_note { play_note(note, tone) }
+            for rem_guesses in (0 .. invocation.attempt_limit).rev() {
+                match guess_note(&mut input, note, None, tone, None, rules,
+                                 &mut stats) {
+                    Guess::Perfect => {
+                        say!(Essential, "Correct!");
+                        player.perfect_count += 1;
+                        break
+                    },
+                    Guess::WrongOctave(_) => {
+                        say!(Essential, "Right note, wrong octave.");
+                        player.right_count += 1;
+                        break
+                    },
+                    Guess::Quit => break 'session,
+                    Guess::Skipped => {
+                        say!(Essential, "Skipped.");
+                        break
+                    },
+                    _ if rem_guesses > 1 => {
+                        say!(Essential, "Try again ({} guesses left)",
+                             rem_guesses);
+                    },
+                    _ if rem_guesses > 0 => {
+                        say!(Essential, "Try again (last guess)");
+                    },
+                    _ => say!(Essential, "Out of guesses."),
+                }
+            }
+        }
+        say!(Essential, "The note was: {}", full_note_name(note));
+        rounds_played += 1;
+    }
+    if rounds_played == 0 {
+        say!(Essential, "No rounds completed.");
+        return
+    }
+    say!(Essential);
+    let mut best = None;
+    for player in players.iter() {
+        let score = compute_score(weights, player.perfect_count,
+                                  player.right_count, 0, rounds_played);
+        say!(Essential, "{}: {}/{} correct, {} wrong-octave: {}% = {}",
+             player.name, player.perfect_count, rounds_played,
+             player.right_count, score, grade_for(score));
+        match best {
+            Some((_, best_score)) if best_score >= score => (),
+            _ => best = Some((player.name.as_str(), score)),
+        }
+    }
+    let (winner, winning_score) = best.unwrap();
+    let tied = players.iter().filter(|player| {
+        compute_score(weights, player.perfect_count, player.right_count, 0,
+                      rounds_played) == winning_score
+    }).count() > 1;
+    if tied { say!(Essential, "It's a tie!") }
+    else { say!(Essential, "{} wins!", winner) }
+}
+
+/// How much each kind of answer is worth. A perfect answer counts fully, so
+/// with the defaults, a wrong-octave or hedged answer counts half.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+struct Weights {
+    perfect: u32,
+    octave: u32,
+    hedge: u32,
+}
+
+const DEFAULT_WEIGHTS: Weights = Weights { perfect: 2, octave: 1, hedge: 1 };
+
+impl Weights {
+    /// How much credit an answer of this weight gets, in words.
+    fn credit(&self, weight: u32) -> String {
+        if weight * 2 == self.perfect { "Half".to_string() }
+        else { format!("{}/{}", weight, self.perfect) }
+    }
+}
+
+/// The score for `earned` credit over `rounds` rounds, where a perfect
+/// round is worth `perfect`. Scores are worked out in 128 bits, so that no
+/// session is long enough, and no weight big enough, to overflow. No rounds
+/// is a score of 0.
+fn percent(earned: u128, rounds: u128, perfect: u128) -> u32 {
+    if rounds == 0 { return 0 }
+    (earned * 100 / rounds).div_ceil(perfect) as u32
+}
+
+fn compute_score(weights: Weights, perfect_count: u32, right_count: u32,
+                 hedged_count: u32, rounds: u32) -> u32 {
+    let [perfect, octave, hedge] = [weights.perfect, weights.octave,
+                                    weights.hedge].map(u128::from);
+    percent(u128::from(perfect_count) * perfect
+            + u128::from(right_count) * octave
+            + u128::from(hedged_count) * hedge, u128::from(rounds), perfect)
+}
+
+/// Like `compute_score`, but each note in the right place in a missed melody
+/// (`melody_credit` of them, in melodies `melody_length` long) is worth its
+/// share of a perfect answer.
+fn compute_melody_score(weights: Weights, perfect_count: u32,
+                        right_count: u32, hedged_count: u32,
+                        melody_credit: u32, melody_length: usize,
+                        rounds: u32) -> u32 {
+    let length = melody_length as u128;
+    let [perfect, octave, hedge] = [weights.perfect, weights.octave,
+                                    weights.hedge].map(u128::from);
+    percent((u128::from(perfect_count) * length + u128::from(melody_credit))
+            * perfect + u128::from(right_count) * length * octave
+            + u128::from(hedged_count) * length * hedge,
+            u128::from(rounds) * length, perfect)
+}
+
+/// For `--octave-distance-credit`: how much of a perfect answer's credit a
+/// wrong-octave answer gets, in quarters, for being one, two, or three
+/// octaves off.
+const OCTAVE_DISTANCE_CREDIT: &[u32] = &[3, 2, 1];
+
+/// How many octaves off a wrong-octave guess at `note` was. A guess too far
+/// off to name counts as one.
+fn octave_distance(guessed: Option<u32>, note: u32) -> u32 {
+    guessed.map_or(1, |guessed| guessed.abs_diff(note) / NOTES_PER_OCTAVE)
+}
+
+/// Like `compute_score`, but each wrong-octave answer gets credit by how many
+/// octaves off it was, from `octave_distances`.
+fn compute_distance_score(weights: Weights, perfect_count: u32,
+                          octave_distances: &[u32], hedged_count: u32,
+                          rounds: u32) -> u32 {
+    let quarters: u128 = octave_distances.iter().map(|&distance| {
+        OCTAVE_DISTANCE_CREDIT.get(distance.saturating_sub(1) as usize)
+            .copied().map_or(0, u128::from)
+    }).sum();
+    let [perfect, hedge] = [weights.perfect, weights.hedge].map(u128::from);
+    percent(u128::from(perfect_count) * perfect * 4 + quarters * perfect
+            + u128::from(hedged_count) * hedge * 4, u128::from(rounds),
+            perfect * 4)
+}
+
+/// How hard a note is to name, for `--difficulty-weighted`, by the same
+/// reckoning as the session's difficulty: black keys are harder, and so is
+/// each octave away from middle C's.
+fn note_difficulty(note: u32) -> u32 {
+    let black = NOTE_NAMES[(note % NOTES_PER_OCTAVE) as usize].len() > 1;
+    let octaves = (note / NOTES_PER_OCTAVE)
+        .abs_diff(MIDDLE_C / NOTES_PER_OCTAVE);
+    1 + black as u32 + octaves
+}
+
+/// Like `compute_score`, but each round counts as much as the hardest of its
+/// notes (see `note_difficulty`).
+fn compute_weighted_score(weights: Weights, records: &[history::RoundRecord])
+                          -> u32 {
+    let (earned, possible) = records.iter().fold((0, 0), |(earned, possible),
+                                                 record| {
+        let difficulty = record.notes.iter().copied().map(note_difficulty)
+            .max().unwrap_or(1) as u128;
+        let credit = match record.outcome {
+            Outcome::Perfect => weights.perfect,
+            Outcome::WrongOctave => weights.octave,
+            Outcome::Hedged => weights.hedge,
+            Outcome::Missed => 0,
+        } as u128;
+        (earned + credit * difficulty, possible + difficulty)
+    });
+    percent(earned, possible, u128::from(weights.perfect))
+}
+
+/// The lowest score that gets each grade, from the top down. Anything less
+/// is an F.
+const GRADES: &[(u32, &str)] = &[
+    (100, "S"), (97, "A+"), (94, "A"), (90, "A-"), (87, "B+"), (84, "B"),
+    (80, "B-"), (77, "C+"), (74, "C"), (70, "C-"), (67, "D+"), (64, "D"),
+    (60, "D-"),
+];
+
+/// How far short of a grade a score can be and still get a sudden-death
+/// round.
+const SUDDEN_DEATH_MARGIN: u32 = 2;
+
+fn grade_for(score: u32) -> &'static str {
+    GRADES.iter().find(|&&(min, _)| score >= min).map_or("F", |&(_, x)| x)
+}
+
+/// The lowest score of the next grade up, if `score` is no more than
+/// `margin` short of it.
+fn grade_within(score: u32, margin: u32) -> Option<u32> {
+    GRADES.iter().rev().map(|&(min, _)| min).find(|&min| min > score)
+        .filter(|&min| min - score <= margin)
+}
+
+/// A rough label for how hard a session's settings are, so a score can be
+/// taken in context. `pitch_classes` are the ones that can come up.
+fn difficulty(invocation: &Invocation, min_note: u32, max_note: u32,
+              pitch_classes: &[u32]) -> &'static str {
+    let mut points: i32 = match invocation.mode {
+        Mode::Learn | Mode::IntervalQuality | Mode::TheoryInterval => 0,
+        Mode::Note | Mode::Octave | Mode::Sing | Mode::Inversion
+            | Mode::ChordQuality | Mode::Duel | Mode::Progression
+            | Mode::Frequency | Mode::Mixed | Mode::CountNotes
+            | Mode::Interval | Mode::Detune => 1,
+        Mode::Melody => 3,
+    };
+    points += match (max_note - min_note) / NOTES_PER_OCTAVE {
+        0 | 1 => 0,
+        2 => 1,
+        3 => 2,
+        _ => 3,
+    };
+    // Black keys are harder to tell apart than white ones.
+    let accidentals = pitch_classes.iter()
+        .any(|pitch_class| NOTE_NAMES[*pitch_class as usize].len() > 1);
+    // (Except in octave mode, where you're told the pitch class.)
+    if invocation.mode != Mode::Octave {
+        if pitch_classes.len() == NOTES_PER_OCTAVE as usize { points += 2 }
+        else if accidentals { points += 1 }
+    }
+    match invocation.attempt_limit {
+        1 => points += 1,
+        x if x > 3 => points -= 1,
+        _ => (),
+    }
+    if invocation.ignore_octave { points -= 1 }
+    if invocation.allow_hedge { points -= 1 }
+    if invocation.blind { points += 1 }
+    match points {
+        x if x <= 1 => "Easy",
+        x if x <= 3 => "Medium",
+        x if x <= 5 => "Hard",
+        _ => "Expert",
+    }
+}
+
+/// What a round is played as: in mixed mode, note mode and sing mode take
+/// turns, starting with note mode.
+fn round_mode(mode: Mode, round: u32) -> Mode {
+    match mode {
+        Mode::Mixed if round.is_multiple_of(2) => Mode::Note,
+        Mode::Mixed => Mode::Sing,
+        mode => mode,
+    }
+}
+
+/// For mixed mode, prints a score for naming notes (recognition) and one for
+/// singing them (production).
+fn print_mixed_breakdown(weights: Weights, records: &[history::RoundRecord]) {
+    for (direction, mode) in [("Recognition", Mode::Note),
+                              ("Production", Mode::Sing)] {
+        let records: Vec<&history::RoundRecord> = records.iter().enumerate()
+            .filter(|&(round, _)| round_mode(Mode::Mixed, round as u32) == mode)
+            .map(|(_, record)| record).collect();
+        if records.is_empty() { continue }
+        let count = |outcome| records.iter()
+            .filter(|x| x.outcome == outcome).count() as u32;
+        let score = compute_score(weights, count(Outcome::Perfect),
+                                  count(Outcome::WrongOctave),
+                                  count(Outcome::Hedged), records.len() as u32);
+        say!(Essential, "{}: {}/{} correct, {}% = {}", direction,
+             count(Outcome::Perfect), records.len(), score, grade_for(score));
+    }
+}
+
+/// Prints how many were right, and returns the resulting score.
+fn print_tally(weights: Weights, perfect_count: u32, right_count: u32,
+               hedged_count: u32, rounds: u32) -> u32 {
+    if weights != DEFAULT_WEIGHTS {
+        say!(Essential, "Scoring: {} for a perfect answer, {} for the wrong \
+                         octave, {} for a hedge.", weights.perfect,
+             weights.octave, weights.hedge);
+    }
+    if hedged_count > 0 {
+        say!(Essential, "You got {}/{} correct. {} credit for {} \
+                         wrong-octave guesses and {} credit for {} hedged \
+                         guesses.",
+             perfect_count, rounds, weights.credit(weights.octave),
+             right_count, weights.credit(weights.hedge).to_lowercase(),
+             hedged_count);
+    }
+    else {
+        say!(Essential, "You got {}/{} correct. {} credit for {} \
+                         wrong-octave guesses.",
+             perfect_count, rounds, weights.credit(weights.octave),
+             right_count);
+    }
+    compute_score(weights, perfect_count, right_count, hedged_count, rounds)
+}
+
+/// Mentions the partial credit from `melody_notes_right`, if there was any.
+fn print_melody_credit(melody_credit: u32, melody_length: usize) {
+    if melody_credit > 0 {
+        say!(Essential, "Partial credit for {} {} in the right place in \
+                         melodies you missed (1/{} each).", melody_credit,
+             if melody_credit == 1 { "note" } else { "notes" },
+             melody_length);
+    }
+}
+
+const PROGRESS_BAR_WIDTH: u32 = 20;
+
+fn progress_bar(done: u32, total: u32) -> String {
+    let filled = (done * PROGRESS_BAR_WIDTH / total.max(1)) as usize;
+    format!("[{}{}]", glyphs().bar_filled.to_string().repeat(filled),
+            glyphs().bar_empty.to_string()
+            .repeat(PROGRESS_BAR_WIDTH as usize - filled))
+}
+
+/// Parses the note given to `--low` or `--high`, or exits if it's no good.
+fn parse_bound(name: &str) -> u32 {
+    match name_to_note(name) {
+        Some(note) if (LOWEST_NOTE ..= HIGHEST_NOTE).contains(&note) => note,
+        _ => {
+            eprintln!("{:?} isn't a note between {} and {} (e.g. {:?}).",
+                      name, full_note_name(LOWEST_NOTE),
+                      full_note_name(HIGHEST_NOTE), example_note());
+            std::process::exit(1)
+        },
+    }
+}
+
+/// Prints every option's value, and whether it came from the command line,
+/// the environment, or the defaults, for `--explain-config`.
+/// `from_config` are the IDs of the options the config file set.
+fn explain_config(command: &ClapCommand, matches: &ArgMatches,
+                  preset: Option<&Preset>, from_config: &[String]) {
+    say!(Essential, "{:<24} {:<20} Source", "Option", "Value");
+    for arg in command.get_arguments() {
+        let id = arg.get_id();
+        if id == "help" || id == "version" || id == "explain-config" {
+            continue
+        }
+        let name = match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => id.to_string(),
+        };
+        let occurrences = matches.occurrences_of(id);
+        let values: Vec<&str> = matches.values_of(id).into_iter().flatten()
+            .collect();
+        let value = if !values.is_empty() { values.join(",") }
+        else if arg.is_takes_value_set() { "(unset)".to_string() }
+        else if occurrences > 1 { format!("yes (x{})", occurrences) }
+        else if occurrences > 0 { "yes".to_string() }
+        else { "no".to_string() };
+        if let Some(preset) = preset.filter(|x| x.sets(matches, id)) {
+            let (_, value) = preset.values().into_iter()
+                .find(|&(x, _)| x == id).unwrap();
+            let preset_name = preset.name.to_possible_value().unwrap();
+            say!(Essential, "{:<24} {:<20} preset ({})", name, value,
+                 preset_name.get_name());
+            continue
+        }
+        let env = arg.get_env().map(|x| x.to_string_lossy());
+        let source = match matches.value_source(id) {
+            _ if from_config.iter().any(|x| x == id)
+                => "config file".to_string(),
+            Some(ValueSource::CommandLine) => match env.as_ref()
+                .filter(|x| std::env::var_os(x.as_ref()).is_some()) {
+                Some(env) => format!("command line (overriding ${})", env),
+                None => "command line".to_string(),
+            },
+            Some(ValueSource::EnvVariable) => {
+                format!("environment (${})", env.unwrap_or_default())
+            },
+            _ if occurrences > 0 => "command line".to_string(),
+            _ => "default".to_string(),
+        };
+        say!(Essential, "{:<24} {:<20} {}", name, value, source);
+    }
+}
+
+/// Rewrites the note names in the help of `command`'s options, which are
+/// written the `c3` way, in the octave convention in use.
+fn convention_help(command: ClapCommand<'static>) -> ClapCommand<'static> {
+    static NOTE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\b([A-H]|Do)([#b]?)([0-9])\b").unwrap()
+    });
+    if naming::octave_convention() == OctaveConvention::C3 { return command }
+    let ids: Vec<&str> = command.get_arguments().map(|arg| arg.get_id())
+        // These name conventions and chords, not notes.
+        .filter(|&id| id != "octave-convention" && id != "from-chord")
+        .collect();
+    let rewrite = |help: Option<&str>| help.map(|help| {
+        let help = NOTE.replace_all(help, |captures: &regex::Captures| {
+            let name = format!("{}{}", &captures[1], &captures[2]);
+            naming::with_octave(&name, captures[3].parse().unwrap())
+        });
+        // Help text has to last as long as the command.
+        &*Box::leak(help.into_owned().into_boxed_str())
+    });
+    ids.into_iter().fold(command, |command, id| {
+        command.mut_arg(id, |arg| {
+            let (help, long_help) = (rewrite(arg.get_help()),
+                                     rewrite(arg.get_long_help()));
+            arg.help(help).long_help(long_help)
+        })
+    })
+}
+
+/// Lists every preset and what it sets, for `--list-presets`.
+fn print_presets() {
+    let command = Invocation::command();
+    for preset in preset::PRESETS {
+        let name = preset.name.to_possible_value().unwrap();
+        say!(Essential, "{}: {}", name.get_name(), preset.description);
+        for (id, value) in preset.values() {
+            let arg = command.get_arguments().find(|x| x.get_id() == id)
+                .unwrap();
+            let flag = match (arg.get_long(), arg.get_short()) {
+                (Some(long), _) => format!("--{}", long),
+                (_, Some(short)) => format!("-{}", short),
+                (None, None) => id.to_string(),
+            };
+            say!(Essential, "    {} {:?}", flag, value);
+        }
+    }
+}
+
+#[allow(clippy::manual_clamp, clippy::manual_div_ceil)]
+fn main() {
+    // WHAT_NOTE_BACKEND is another name for WHAT_NOTE_PLAYBACK.
+    if std::env::var_os("WHAT_NOTE_PLAYBACK").is_none() {
+        if let Some(backend) = std::env::var_os("WHAT_NOTE_BACKEND") {
+            std::env::set_var("WHAT_NOTE_PLAYBACK", backend);
+        }
+    }
+    let mut command = Invocation::command();
+    // The help writes notes the way `--octave-convention` says, so that (and
+    // the config file it might be in) has to be found out before the command
+    // line is parsed for real, which is when the help is shown.
+    let lenient = command.clone().ignore_errors(true)
+        .disable_help_flag(true).disable_version_flag(true);
+    let early = lenient.clone().get_matches();
+    let config_path = if early.is_present("no-config") { None }
+    else {
+        early.value_of("config").map(PathBuf::from)
+            .or_else(config::default_path)
+    };
+    let mut argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let mut from_config = vec![];
+    // The default config file doesn't have to be there, but one that was
+    // asked for does.
+    if let Some(path) = config_path
+        .filter(|path| early.is_present("config") || path.exists()) {
+        let (args, ids) = config::args(&command, &early, &path)
+            .unwrap_or_else(|x| {
+                eprintln!("{}", x);
+                std::process::exit(1)
+            });
+        argv.splice(1 .. 1, args);
+        from_config = ids;
+    }
+    let early = lenient.get_matches_from(argv.iter());
+    if let Some(convention) = early.value_of("octave-convention")
+        .and_then(|x| OctaveConvention::from_str(x, true).ok()) {
+        naming::set_octave_convention(convention);
+        command = convention_help(command);
+    }
+    let matches = command.clone().get_matches_from(argv);
+    let mut invocation = Invocation::from_arg_matches(&matches)
+        .unwrap_or_else(|x| x.exit());
+    if invocation.list_presets {
+        print_presets();
+        return
+    }
+    if invocation.midi_in.is_some()
+        && !matches!(invocation.mode, Mode::Note | Mode::Duel) {
+        eprintln!("--midi-in only works in note and duel modes.");
+        std::process::exit(1)
+    }
+    if invocation.list_midi {
+        let devices = midi::list();
+        if devices.is_empty() { say!(Essential, "No MIDI devices found.") }
+        for device in devices { say!(Essential, "{}", device.display()) }
+        return
+    }
+    let preset = invocation.preset.map(Preset::get);
+    if let Some(preset) = preset { preset.apply(&mut invocation, &matches) }
+    naming::set_naming(invocation.naming);
+    naming::set_octave_convention(invocation.octave_convention);
+    if invocation.explain_config {
+        explain_config(&command, &matches, preset, &from_config);
+        say!(Essential, "(So {} is {}Hz.)", full_note_name(BASE_NOTE as u32),
+             invocation.tuning.unwrap_or(invocation.pitch_standard.freq()));
+        return
+    }
+    glyphs::set_ascii(invocation.ascii);
+    verbosity::set_verbosity(invocation.quiet, invocation.verbose);
+    if invocation.duration.is_nan() || invocation.duration < MIN_DURATION {
+        eprintln!("The duration must be at least {} seconds.", MIN_DURATION);
+        std::process::exit(1)
+    }
+    let base_freq = invocation.tuning
+        .unwrap_or(invocation.pitch_standard.freq());
+    if !(base_freq.is_finite() && base_freq > 0.0) {
+        eprintln!("--tuning has to be a positive number of Hz.");
+        std::process::exit(1)
+    }
+    tuning::set_base_freq(base_freq);
+    playback::set_backend(invocation.playback);
+    if let Some(path) = invocation.midi_out.as_ref() {
+        if let Err(x) = midi::open(path) {
+            eprintln!("{}", x);
+            std::process::exit(1)
+        }
+    }
+    if invocation.max_test_count == 0 {
+        eprintln!("--max-test-count has to be at least 1.");
+        std::process::exit(1)
+    }
+    if invocation.test_count > invocation.max_test_count {
+        eprintln!("Warning: {} notes is too many; testing {} instead (see \
+                   --max-test-count).", invocation.test_count,
+                  invocation.max_test_count);
+        invocation.test_count = invocation.max_test_count;
+    }
+    if invocation.metronome == Some(0) {
+        eprintln!("The metronome needs at least one beat per minute.");
+        std::process::exit(1);
+    }
+    if !invocation.freq_tolerance.is_finite()
+        || invocation.freq_tolerance <= 0.0 {
+        eprintln!("--freq-tolerance has to be more than 0 percent.");
+        std::process::exit(1)
+    }
+    if !(MIN_DETUNE ..= MAX_TOLERANCE_CENTS as u32)
+        .contains(&invocation.max_detune) {
+        eprintln!("--max-detune has to be between {} and {} cents.",
+                  MIN_DETUNE, MAX_TOLERANCE_CENTS);
+        std::process::exit(1)
+    }
+    if !invocation.detune_tolerance.is_finite()
+        || invocation.detune_tolerance < 0.0 {
+        eprintln!("--detune-tolerance can't be negative.");
+        std::process::exit(1)
+    }
+    if !(invocation.tolerance_cents > 0.0
+         && invocation.tolerance_cents <= MAX_TOLERANCE_CENTS) {
+        eprintln!("The tolerance must be more than 0 and at most {} cents.",
+                  MAX_TOLERANCE_CENTS);
+        std::process::exit(1);
+    }
+    let octaves = invocation.octaves.min(5).max(1);
+    let octaves_below = octaves/2;
+    let octaves_above = (octaves+1)/2;
+    let mut min_note = match invocation.low.as_ref() {
+        Some(low) => parse_bound(low),
+        None => MIDDLE_C - octaves_below * NOTES_PER_OCTAVE,
+    };
+    let mut max_note = match invocation.high.as_ref() {
+        Some(high) => parse_bound(high),
+        None => MIDDLE_C + octaves_above * NOTES_PER_OCTAVE,
+    };
+    if min_note > max_note {
+        eprintln!("The lowest note ({}) is higher than the highest note ({}).",
+                  full_note_name(min_note), full_note_name(max_note));
+        std::process::exit(1)
+    }
+    // With `--transpose`, each note's other name (the sounding one, or the
+    // written one) has to be nameable too.
+    let shift = match invocation.transpose_target {
+        TransposeTarget::Written => invocation.transpose,
+        TransposeTarget::Sounding => -invocation.transpose,
+    };
+    let lowest = (LOWEST_NOTE as i64 - shift as i64).max(LOWEST_NOTE as i64);
+    let highest = (HIGHEST_NOTE as i64 - shift as i64)
+        .min(HIGHEST_NOTE as i64);
+    if lowest > max_note as i64 || highest < min_note as i64 {
+        eprintln!("Notes can only be named from {} to {}, so with --transpose \
+                   {}, none of the notes from {} to {} can be.",
+                  full_note_name(LOWEST_NOTE), full_note_name(HIGHEST_NOTE),
+                  invocation.transpose, full_note_name(min_note),
+                  full_note_name(max_note));
+        std::process::exit(1)
+    }
+    if (min_note as i64) < lowest {
+        min_note = lowest as u32;
+        eprintln!("Notes can only be named down to {}, so with --transpose \
+                   {}, the lowest note will be {}.",
+                  full_note_name(LOWEST_NOTE), invocation.transpose,
+                  full_note_name(min_note));
+    }
+    if (max_note as i64) > highest {
+        max_note = highest as u32;
+        eprintln!("Notes can only be named up to {}, so with --transpose \
+                   {}, the highest note will be {}.",
+                  full_note_name(HIGHEST_NOTE), invocation.transpose,
+                  full_note_name(max_note));
+    }
+    let spellings = if let Some(name) = invocation.from_scale.as_ref() {
+        let spellings = scale::parse_scale(name).unwrap_or_else(|| {
+            eprintln!("{:?} isn't a scale I know (e.g. \"G major\"). The \
+                       kinds of scale I know are: {}", name,
+                      scale::scale_kind_names().join(", "));
+            std::process::exit(1)
+        });
+        Some(spellings)
+    }
+    else if let Some(name) = invocation.from_chord.as_ref() {
+        let spellings = parse_chord_symbol(name).unwrap_or_else(|| {
+            eprintln!("{:?} isn't a chord I know (e.g. \"C7\", \"F#m\", \
+                       \"Bbmaj7\").", name);
+            std::process::exit(1)
+        });
+        Some(spellings)
+    }
+    else { None };
+    if invocation.weight_perfect == 0
+        || invocation.weight_octave > invocation.weight_perfect
+        || invocation.weight_hedge > invocation.weight_perfect {
+        eprintln!("A perfect answer has to be worth something, and at least \
+                   as much as any other answer.");
+        std::process::exit(1)
+    }
+    if invocation.notation_strict && spellings.is_none() {
+        eprintln!("--notation-strict needs --from-scale or --from-chord, to \
+                   know how notes should be spelled.");
+        std::process::exit(1)
+    }
+    if let Some(spellings) = spellings.as_ref() {
+        if !(min_note ..= max_note).any(|note| spellings.iter()
+                                         .any(|x| x.pitch_class()
+                                              == note % NOTES_PER_OCTAVE)) {
+            eprintln!("None of those notes are between {} and {}.",
+                      full_note_name(min_note), full_note_name(max_note));
+            std::process::exit(1)
+        }
+    }
+    let tonic = invocation.tonic.as_ref().map(|name| {
+        match Spelling::parse_prefix(name.trim()) {
+            Some((tonic, "")) => tonic,
+            _ => {
+                eprintln!("--tonic should be a note name without an octave, \
+                           like \"D\" or \"Bb\".");
+                std::process::exit(1)
+            },
+        }
+    }).or_else(|| spellings.as_ref().and_then(|x| x.first().copied()));
+    if invocation.temperament == Temperament::Just {
+        tuning::set_tonic(tonic.map_or(min_note % NOTES_PER_OCTAVE,
+                                       Spelling::pitch_class));
+    }
+    else if invocation.tonic.is_some() {
+        eprintln!("--tonic only matters with --temperament just.");
+        std::process::exit(1)
+    }
+    let tone = Tone { duration: invocation.duration,
+                      waveform: invocation.waveform,
+                      antialias: !invocation.no_antialias,
+                      transpose: match invocation.transpose_target {
+                          TransposeTarget::Written => invocation.transpose,
+                          TransposeTarget::Sounding => 0,
+                      },
+                      out_of_range: invocation.out_of_range };
+    if invocation.test_audio {
+        test_audio(Tone { transpose: 0, ..tone });
+        return
+    }
+    if invocation.test_stereo {
+        test_stereo(Tone { transpose: 0, ..tone });
+        return
+    }
+    if invocation.print_notes {
+        print_notes(min_note, max_note, spellings.as_deref(), tone);
+        return
+    }
+    let history_path = invocation.history.clone()
+        .or_else(history::default_path);
+    if invocation.lifetime || invocation.stats || invocation.trend.is_some() {
+        let path = match history_path.as_ref() {
+            Some(x) => x,
+            None => {
+                eprintln!("Couldn't work out where the history file is. Use \
+                           --history to say.");
+                std::process::exit(1)
+            },
+        };
+        match history::load(path) {
+            Ok(records) => {
+                let stdout = stdout();
+                let mut out = stdout.lock();
+                let result = (|| {
+                    if invocation.lifetime {
+                        history::print_lifetime_stats(
+                            &mut out, &history::lifetime_stats(&records))?;
+                    }
+                    if invocation.stats {
+                        history::print_note_stats(&mut out, &records)?;
+                    }
+                    if let Some(sessions) = invocation.trend {
+                        history::print_trend(&mut out, &records, sessions)?;
+                    }
+                    out.flush()
+                })();
+                match result {
+                    // Whatever we were piped into has seen all it wants.
+                    Err(x) if x.kind() == ErrorKind::BrokenPipe => (),
+                    Err(x) => {
+                        eprintln!("Couldn't write the statistics: {}", x);
+                        std::process::exit(1)
+                    },
+                    Ok(()) => (),
+                }
+            },
+            Err(x) => {
+                eprintln!("Couldn't read {:?}: {}", path, x);
+                std::process::exit(1)
+            },
+        }
+        return
+    }
+    let checkpoint_path = checkpoint::default_path();
+    if invocation.discard {
+        if let Some(path) = checkpoint_path.as_ref() {
+            if let Err(x) = checkpoint::remove(path) {
+                eprintln!("Couldn't remove {:?}: {}", path, x);
+                std::process::exit(1)
+            }
+        }
+    }
+    let mut session = Session::default();
+    let mut rng = match invocation.seed {
+        Some(seed) => ChaCha12Rng::seed_from_u64(seed),
+        None => ChaCha12Rng::from_entropy(),
+    };
+    // Kept apart from `rng`, so that it doesn't change which notes come up.
+    let mut spelling_rng = rng.clone();
+    spelling_rng.set_stream(1);
+    let mut jitter_rng = rng.clone();
+    jitter_rng.set_stream(2);
+    if invocation.resume {
+        let Some(path) = checkpoint_path.as_ref() else {
+            eprintln!("There's no interrupted session to resume.");
+            std::process::exit(1)
+        };
+        let checkpoint = match checkpoint::load(path) {
+            Ok(x) => x,
+            Err(x) if x.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("There's no interrupted session to resume.");
+                std::process::exit(1)
+            },
+            Err(x) => {
+                eprintln!("Couldn't read {:?}: {}", path, x);
+                std::process::exit(1)
+            },
+        };
+        invocation.mode = checkpoint.mode;
+        invocation.test_count = checkpoint.test_count;
+        min_note = checkpoint.min_note;
+        max_note = checkpoint.max_note;
+        rng = ChaCha12Rng::from_seed(checkpoint.seed);
+        rng.set_word_pos(checkpoint.word_pos);
+        spelling_rng = rng.clone();
+        spelling_rng.set_stream(1);
+        spelling_rng.set_word_pos(checkpoint.spelling_word_pos);
+        jitter_rng = rng.clone();
+        jitter_rng.set_stream(2);
+        jitter_rng.set_word_pos(checkpoint.jitter_word_pos);
+        session = checkpoint.session;
+        say!(Essential, "Resuming at round {} of {}.",
+             session.rounds_played + 1, invocation.test_count);
+    }
+    else if let Some(list) = invocation.must_include.as_ref() {
+        if !matches!(invocation.mode, Mode::Note | Mode::Octave | Mode::Sing) {
+            eprintln!("--must-include only works in note, octave, and sing \
+                       modes.");
+            std::process::exit(1)
+        }
+        let notes: Vec<u32> = split_note_list(list).into_iter().map(|name| {
+            let name = name.trim();
+            match name_to_note(name) {
+                Some(note) if (min_note ..= max_note).contains(&note) => note,
+                _ => {
+                    eprintln!("{:?} isn't a note between {} and {} (e.g. \
+                               {:?}).", name, full_note_name(min_note),
+                              full_note_name(max_note), example_note());
+                    std::process::exit(1)
+                },
+            }
+        }).collect();
+        if notes.len() > invocation.test_count as usize {
+            eprintln!("There are {} notes to include, but only {} rounds. \
+                       (Use -t to change the number of rounds.)",
+                      notes.len(), invocation.test_count);
+            std::process::exit(1)
+        }
+        let rounds = rand::seq::index::sample(
+            &mut rng, invocation.test_count as usize, notes.len());
+        session.scheduled = rounds.iter().zip(notes)
+            .map(|(round, note)| (round as u32, note)).collect();
+    }
+    if let Some(minutes) = invocation.duration_minutes {
+        if minutes.is_nan() || minutes <= 0.0 {
+            eprintln!("--duration-minutes has to be more than zero.");
+            std::process::exit(1)
+        }
+        if invocation.mode == Mode::Duel {
+            eprintln!("Duel mode can't be timed with --duration-minutes.");
+            std::process::exit(1)
+        }
+        // The clock decides when to stop instead.
+        invocation.test_count = u32::MAX;
+    }
+    if !(2 ..= MAX_MELODY_LENGTH).contains(&invocation.melody_length) {
+        eprintln!("--melody-length has to be between 2 and {}.",
+                  MAX_MELODY_LENGTH);
+        std::process::exit(1)
+    }
+    if invocation.max_cluster < 2 {
+        eprintln!("--max-cluster has to be at least 2.");
+        std::process::exit(1)
+    }
+    if let Some(goal) = invocation.until_correct {
+        if goal == 0 {
+            eprintln!("--until-correct has to be at least 1.");
+            std::process::exit(1)
+        }
+        if invocation.mode == Mode::Duel {
+            eprintln!("Duel mode can't be played --until-correct.");
+            std::process::exit(1)
+        }
+        // The goal decides when to stop, and this is just a backstop.
+        invocation.test_count = invocation.max_test_count;
+    }
+    if let Some(dir) = invocation.record_attempts.as_ref() {
+        if !matches!(invocation.mode, Mode::Sing | Mode::Mixed) {
+            eprintln!("--record-attempts only works in sing and mixed modes.");
+            std::process::exit(1)
+        }
+        if let Err(x) = std::fs::create_dir_all(dir) {
+            eprintln!("Couldn't create {:?}: {}", dir, x);
+            std::process::exit(1)
+        }
+    }
+    if invocation.gap.is_nan() || invocation.gap < 0.0 {
+        eprintln!("--gap can't be negative.");
+        std::process::exit(1)
+    }
+    if invocation.lives == Some(0) {
+        eprintln!("--lives has to be at least 1.");
+        std::process::exit(1)
+    }
+    if invocation.lives.is_some() && invocation.mode == Mode::Duel {
+        eprintln!("Duel mode doesn't have --lives.");
+        std::process::exit(1)
+    }
+    let interval_mode = matches!(invocation.mode, Mode::Interval
+                                 | Mode::TheoryInterval
+                                 | Mode::IntervalQuality);
+    if invocation.no_repeat.is_some() && !interval_mode
+        && !matches!(invocation.mode, Mode::Note | Mode::Octave | Mode::Sing) {
+        eprintln!("--no-repeat only works in note, octave, sing, and interval \
+                   modes.");
+        std::process::exit(1)
+    }
+    if (invocation.fixed_root.is_some()
+        || invocation.interval_direction != IntervalDirection::Both)
+        && !interval_mode {
+        eprintln!("--fixed-root and --interval-direction only work in the \
+                   interval modes.");
+        std::process::exit(1)
+    }
+    if let Some(root) = invocation.fixed_root() {
+        if !(min_note ..= max_note).contains(&root) {
+            eprintln!("--fixed-root has to be between {} and {}.",
+                      full_note_name(min_note), full_note_name(max_note));
+            std::process::exit(1)
+        }
+    }
+    if (invocation.note_weights.is_some() || invocation.retry_last)
+        && matches!(invocation.mode, Mode::Interval | Mode::TheoryInterval
+                    | Mode::IntervalQuality | Mode::Inversion
+                    | Mode::ChordQuality) {
+        eprintln!("--weights and --retry-last don't work in that mode.");
+        std::process::exit(1)
+    }
+    let mut note_weights = invocation.note_weights.as_ref().map(|path| {
+        let default = if invocation.weights_exclusive { 0.0 } else { 1.0 };
+        NoteWeights::read(path, default).unwrap_or_else(|x| {
+            eprintln!("{}", x);
+            std::process::exit(1)
+        })
+    });
+    if invocation.retry_last {
+        let path = invocation.history.clone().or_else(history::default_path);
+        let records = match path.as_ref().map(|path| history::load(path)) {
+            Some(Ok(x)) => x,
+            Some(Err(x)) => {
+                eprintln!("Couldn't read {:?}: {}", path.unwrap(), x);
+                vec![]
+            },
+            None => vec![],
+        };
+        let missed: Vec<u32> = history::last_missed(&records).into_iter()
+            .filter(|note| (min_note ..= max_note).contains(note)).collect();
+        if missed.is_empty() {
+            say!(Essential, "You didn't miss any notes between {} and {} last \
+                             session, so any of them can come up.",
+                 full_note_name(min_note), full_note_name(max_note));
+        }
+        else {
+            say!(Normal, "Practicing the {} {} you missed last session.",
+                 missed.len(), if missed.len() == 1 { "note" } else { "notes" });
+            note_weights = Some(NoteWeights::only(&missed));
+        }
+    }
+    let schedule_path = invocation.schedule.clone()
+        .or_else(review::default_path);
+    let mut schedule = None;
+    if invocation.spaced_repetition {
+        if !matches!(invocation.mode, Mode::Note | Mode::Octave | Mode::Sing) {
+            eprintln!("--spaced-repetition only works in note, octave, and \
+                       sing modes.");
+            std::process::exit(1)
+        }
+        let Some(path) = schedule_path.as_ref() else {
+            eprintln!("Couldn't work out where to keep the schedule. Use \
+                       --schedule.");
+            std::process::exit(1)
+        };
+        let loaded = review::Schedule::load(path).unwrap_or_else(|x| {
+            eprintln!("{}", x);
+            std::process::exit(1)
+        });
+        let now = history::now();
+        let due = loaded.due_count(min_note, max_note, now);
+        say!(Normal, "{} of the {} notes between {} and {} {} due for review.",
+             due, max_note - min_note + 1, full_note_name(min_note),
+             full_note_name(max_note), if due == 1 { "is" } else { "are" });
+        note_weights = Some(loaded.weights(now));
+        schedule = Some(loaded);
+    }
+    // Leave at least one note that can be picked.
+    let pool = (min_note ..= max_note).filter(|&note| {
+        spellings.as_ref().is_none_or(|spellings| spellings.iter()
+            .any(|x| x.pitch_class() == note % NOTES_PER_OCTAVE))
+            && note_weights.as_ref().is_none_or(|x| x.weight(note) > 0.0)
+    }).count();
+    if pool == 0 {
+        eprintln!("--weights gives every note between {} and {} a weight of \
+                   0.", full_note_name(min_note), full_note_name(max_note));
+        std::process::exit(1)
+    }
+    let pool = if interval_mode {
+        interval_pool(min_note, max_note, invocation.fixed_root(),
+                      invocation.interval_direction)
+    }
+    else { pool };
+    let no_repeat = invocation.no_repeat.unwrap_or(0)
+        .min(pool.saturating_sub(1));
+    // What `--no-repeat` doesn't repeat: the interval, or the note.
+    let identity = |notes: &[u32]| if interval_mode {
+        notes[0].abs_diff(notes[1])
+    }
+    else { notes[0] };
+    if let Some(path) = invocation.answers.as_ref() {
+        if matches!(invocation.mode, Mode::Sing | Mode::Mixed
+                    | Mode::Detune) {
+            eprintln!("Sing, mixed, and detune modes can't be graded from an \
+                       answer file.");
+            std::process::exit(1)
+        }
+        if invocation.mode == Mode::Duel {
+            eprintln!("Duel mode can't be graded from an answer file.");
+            std::process::exit(1)
+        }
+        if invocation.mode == Mode::Learn {
+            eprintln!("Learn mode doesn't have any answers to grade.");
+            std::process::exit(1)
+        }
+        if invocation.mode == Mode::Progression {
+            eprintln!("Progression mode can't be graded from an answer file.");
+            std::process::exit(1)
+        }
+        run_assessment(&invocation, path, &mut rng, min_note, max_note,
+                       spellings.as_deref(), note_weights.as_ref());
+        return
+    }
+    if let Some(path) = invocation.worksheet.as_ref() {
+        if matches!(invocation.mode, Mode::Sing | Mode::Learn
+                    | Mode::Progression | Mode::Detune | Mode::Mixed) {
+            eprintln!("That mode can't be done on a worksheet.");
+            std::process::exit(1)
+        }
+        if invocation.seed.is_none() {
+            eprintln!("--worksheet needs a --seed, so that the same notes \
+                       can be played for it later.");
+            std::process::exit(1)
+        }
+        write_worksheet(&invocation, path, &mut rng, min_note, max_note,
+                        spellings.as_deref(), note_weights.as_ref());
+        return
+    }
+    let _prerendered = invocation.prerender
+        .then(|| prerender::render(min_note, max_note, tone));
+    let _stream = invocation.low_latency.then(stream::open);
+    if invocation.mode == Mode::Duel {
+        run_duel(&invocation, &mut rng, min_note, max_note,
+                 spellings.as_deref(), note_weights.as_ref(), tone);
+        return
+    }
+    if invocation.mode == Mode::Learn {
+        run_learn(&invocation, &mut rng, min_note, max_note,
+                  spellings.as_deref(), note_weights.as_ref(), tone);
+        return
+    }
+    if invocation.mode == Mode::Progression {
+        if invocation.from_chord.is_some() {
+            eprintln!("Progression mode needs a scale for its key, not a \
+                       chord.");
+            std::process::exit(1)
+        }
+        run_progression(&invocation, &mut rng, min_note, max_note,
+                        spellings.as_deref(), tone);
+        return
+    }
+    if !invocation.no_range_info {
+        say!(Normal, " Lowest note we'll play: {}", full_note_name(min_note));
+        say!(Normal, "Highest note we'll play: {}", full_note_name(max_note));
+    }
+    let mut events = match Events::open(invocation.output_fd.as_deref()) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("Couldn't open {:?}: {}",
+                      invocation.output_fd.as_ref().unwrap(), x);
+            std::process::exit(1)
+        },
+    };
+    let stdin = stdin();
+    let mut input = Transcript::new(open_input(&invocation, &stdin));
+    let session_start = Instant::now();
+    let deadline = invocation.duration_minutes.map(|minutes| {
+        session_start + Duration::from_secs_f32(minutes * 60.0)
+    });
+    let feedback = if invocation.distance_hint { Feedback::Full }
+    else { invocation.feedback };
+    let rules = NoteRules { ignore_octave: invocation.ignore_octave,
+                            allow_hedge: invocation.allow_hedge,
+                            loop_note: invocation.loop_note,
+                            prev_limit: invocation.prev_limit };
+    // For `--sudden-death`, once it starts: the score it's for, and the
+    // session as it was before, since the round itself doesn't count.
+    let mut sudden_death: Option<(u32, Session)> = None;
+    // Whether there's a checkpoint of this session to remove at the end.
+    // Until this session saves one, any that's there is somebody else's.
+    let mut checkpointed = invocation.resume;
+    if !invocation.resume
+        && checkpoint_path.as_ref().is_some_and(|x| x.exists()) {
+        say!(Normal, "(There's an interrupted session. Use --resume to pick \
+                      it back up.)");
+    }
+    'session: for round in session.rounds_played .. {
+        let mode = round_mode(invocation.mode, round);
+        if invocation.until_correct
+            .is_some_and(|goal| session.perfect_count >= goal) {
+            break
+        }
+        if round >= invocation.test_count {
+            if round > invocation.test_count || !invocation.sudden_death {
+                break
+            }
+            let score = session.score(invocation.weights(),
+                                      invocation.octave_distance_credit,
+                                      invocation.melody_length);
+            let Some(target) = grade_within(score, SUDDEN_DEATH_MARGIN)
+            else { break };
+            events.send(&format!("round {}", round + 1));
+            say!(Normal, "--- Sudden death!");
+            say!(Essential, "Your score is {}% = {}. Get this one right, with \
+                             one guess, to bring it up to {}% = {}.", score,
+                 grade_for(score), target, grade_for(target));
+            sudden_death = Some((target, session.clone()));
+        }
+        else if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                say!(Essential, "Time's up!");
+                break
+            }
+            events.send(&format!("round {}", round + 1));
+            if invocation.no_progress { say!(Normal, "---") }
+            else {
+                let left = (deadline - now).as_secs();
+                say!(Normal, "--- Round {} ({}:{:02} left)", round + 1,
+                     left / 60, left % 60);
+            }
+        }
+        else if let Some(goal) = invocation.until_correct {
+            events.send(&format!("round {}", round + 1));
+            if invocation.no_progress { say!(Normal, "---") }
+            else {
+                say!(Normal, "--- Round {} ({}/{} right)", round + 1,
+                     session.perfect_count, goal);
+            }
+        }
+        else if invocation.no_progress {
+            events.send(&format!("round {} {}", round + 1,
+                                 invocation.test_count));
+            say!(Normal, "---");
+        }
+        else {
+            events.send(&format!("round {} {}", round + 1,
+                                 invocation.test_count));
+            say!(Normal, "--- Round {}/{} {}", round + 1, invocation.test_count,
+                 progress_bar(round, invocation.test_count));
+        }
+        let scheduled = session.scheduled.iter()
+            .find(|(index, _)| *index == round).map(|&(_, note)| note);
+        let recent: Vec<u32> = session.round_records.iter().rev()
+            .take(no_repeat).map(|record| identity(&record.notes)).collect();
+        let round = match scheduled {
+            Some(note) => Round { notes: vec![note], chord: None },
+            None => loop {
+                let round = draw_round(mode, &mut rng, min_note,
+                                       max_note, &session.boosts,
+                                       spellings.as_deref(),
+                                       note_weights.as_ref(),
+                                       invocation.round_shape());
+                if !recent.contains(&identity(&round.notes)) { break round }
+            },
+        };
+        let notes = round.notes;
+        let note = notes[0];
+        // With `--mixed-accidentals`, each black key in this round gets
+        // spelled with a sharp or a flat at random.
+        let mixed: Vec<Spelling> = notes.iter()
+            .filter(|_| invocation.mixed_accidentals && spellings.is_none())
+            .filter_map(|&note| {
+                let sharp = Spelling::sharp(note % NOTES_PER_OCTAVE);
+                if sharp.accidental == 0 { None }
+                else if spelling_rng.gen() { Some(sharp.above(1, 0)) }
+                else { Some(sharp) }
+            }).collect();
+        let spelling_of = |note| spelling_in(spellings.as_deref(), note)
+            .or_else(|| spelling_in(Some(&mixed), note));
+        let staff_spelling = |note| spelling_of(note)
+            .unwrap_or_else(|| staff::default_spelling(note));
+        let names: Vec<String> = notes.iter().map(|&note| {
+            match spelling_of(note) {
+                Some(x) if invocation.ignore_octave => x.name(),
+                Some(x) => x.full_name(note),
+                None if invocation.ignore_octave => note_name(note),
+                None => full_note_name(note),
+            }
+        }).collect();
+        let cluster_freqs: Vec<f32> = notes.iter()
+            .map(|&note| note_freq(note)).collect();
+        // How many cents out of tune the note is played, in detune mode.
+        let detune = if mode != Mode::Detune
+            || rng.gen_bool(IN_TUNE_CHANCE) { 0 }
+        else {
+            let cents = rng.gen_range(MIN_DETUNE ..= invocation.max_detune)
+                as i32;
+            if rng.gen() { cents } else { -cents }
+        };
+        let detuned_freq = pitch_freq(note as f32 + tuning::just_offset(note)
+                                      + detune as f32 / 100.0);
+        // What the first note actually sounds as, for frequency mode.
+        let played_freq = tone.audible(note_freq(note))
+            .unwrap_or_else(|| tone.sounding(note_freq(note)));
+        if invocation.jitter > 0
+            && !matches!(mode, Mode::TheoryInterval | Mode::Sing) {
+            let delay = jitter_rng.gen_range(0 ..= invocation.jitter);
+            sleep(Duration::from_millis(delay.into()));
+        }
+        let (answer_kind, answer) = match mode {
+            Mode::Note => {
+                if invocation.staff {
+                    say!(Essential, "{}", staff::render_staff(
+                        note, staff_spelling(note), false));
+                }
+                if !invocation.loop_note { play_note(note, tone) }
+                ("note", names[0].clone() + &transposed_name(&invocation,
+                                                              note))
+            },
+            Mode::Octave => {
+                let name = match spelling_of(note) {
+                    Some(x) => x.name(),
+                    None => note_name(note),
+                };
+                say!(Essential, "This is {} {}. Which octave?",
+                     if name.starts_with(['A', 'E', 'F']) { "an" } else { "a" },
+                     name);
+                play_note(note, tone);
+                ("note", match spelling_of(note) {
+                    Some(x) => x.full_name(note),
+                    None => full_note_name(note),
+                })
+            },
+            Mode::TheoryInterval => {
+                say!(Essential, "{} {}", full_note_name(notes[0]),
+                     full_note_name(notes[1]));
+                let interval = notes[0].abs_diff(notes[1]);
+                ("interval", format!("{} ({})", interval_name(interval),
+                                     names.join(" ")))
+            },
+            Mode::Interval => {
+                play_melody(&notes, None, false, tone);
+                let interval = notes[0].abs_diff(notes[1]);
+                ("interval", format!("{} ({})", interval_name(interval),
+                                     names.join(" ")))
+            },
+            Mode::IntervalQuality => {
+                play_melody(&notes, None, false, tone);
+                let interval = notes[0].abs_diff(notes[1]);
+                ("interval", format!("{} ({}: {})", interval_quality(interval),
+                                     interval_name(interval),
+                                     names.join(" ")))
+            },
+            Mode::Melody => {
+                play_melody(&notes, invocation.metronome,
+                            invocation.portamento, tone);
+                ("melody", names.join(" "))
+            },
+            Mode::Frequency => {
+                play_note(note, tone);
+                ("frequency", format!("{:.1}Hz (nearest note: {})",
+                                      played_freq,
+                                      nearest_note(played_freq)
+                                      .map_or("none".to_string(),
+                                              full_note_name)))
+            },
+            Mode::Detune => {
+                say!(Essential, "Is this {} sharp, flat, or in tune?",
+                     full_note_name(note));
+                play_freqs(&[detuned_freq], tone);
+                ("note", format!("{} ({})", detune_name(detune),
+                                 full_note_name(note)))
+            },
+            Mode::Sing => {
+                say!(Essential, "Your note is {}.", full_note_name(note));
+                ("note", format!("{} ({:.1}Hz)", full_note_name(note),
+                                 note_freq(note)))
+            },
+            Mode::Inversion => {
+                let chord = round.chord.unwrap();
+                play_chord(&freqs(&notes, chord.root, invocation.temperament),
+                           invocation.arpeggiate, tone);
+                ("inversion", format!("{} ({}: {})",
+                                      inversion_name(chord.inversion),
+                                      chord.name(), names.join(" ")))
+            },
+            Mode::ChordQuality => {
+                let chord = round.chord.unwrap();
+                play_chord(&freqs(&notes, chord.root, invocation.temperament),
+                           invocation.arpeggiate, tone);
+                ("chord", format!("{} ({}: {})",
+                                  chord.quality.abbreviation(), chord.name(),
+                                  names.join(" ")))
+            },
+            Mode::CountNotes => {
+                play_chord(&cluster_freqs, false, tone);
+                ("count", format!("{} ({})", notes.len(), names.join(" ")))
+            },
+            Mode::Duel | Mode::Learn | Mode::Progression | Mode::Mixed
+                => unreachable!(),
+        };
+        let attempt_limit = if invocation.blind || sudden_death.is_some() { 1 }
+        else if invocation.variable_attempts
+            && invocation.attempt_limit > 1 {
+            let limit = rng.gen_range(1 ..= invocation.attempt_limit);
+            if limit == 1 && !invocation.smart_attempts {
+                say!(Essential, "Only one guess this round!")
+            }
+            else if !invocation.smart_attempts {
+                say!(Essential, "You get {} guesses this round.", limit)
+            }
+            limit
+        } else { invocation.attempt_limit };
+        let attempt_limit = if invocation.smart_attempts && !invocation.blind
+            && sudden_death.is_none() {
+            let reasons = extra_attempt_reasons(&notes);
+            let limit = attempt_limit + reasons.len() as u32;
+            if limit == 1 { say!(Essential, "Only one guess this round!") }
+            else if reasons.is_empty() {
+                say!(Essential, "You get {} guesses this round.", limit);
+            }
+            else {
+                say!(Essential, "You get {} guesses this round ({}).", limit,
+                     reasons.join(", "));
+            }
+            limit
+        } else { attempt_limit };
+        let reflection = if invocation.reflect {
+            say!(Essential, "How does it sound? (Anything you like, e.g. \
+                             \"bright, high\".)");
+            match read_input(&mut input) {
+                Some(text) if text.trim().is_empty() => None,
+                Some(text) => Some(text.trim().to_string()),
+                None => break 'session,
+            }
+        }
+        else { None };
+        let start = Instant::now();
+        let mut response_time = None;
+        let stats = &mut session.breakdown[(note % NOTES_PER_OCTAVE) as usize];
+        let mut outcome = Outcome::Missed;
+        let mut out_of_lives = false;
+        let mut melody_credit = 0;
+        let mut guesses = vec![];
+        // How many octaves off a wrong-octave guess was. (If we can't
+        // tell, one.)
+        let previous = session.round_records.last()
+            .filter(|_| !invocation.blind).map(|record| record.notes[0]);
+        for rem_guesses in (0 .. attempt_limit).rev() {
+            let mut stdout = stdout();
+            let mut guess_input = GuessInput { lines: &mut input,
+                                               prompt: &mut stdout };
+            let guess = match mode {
+                Mode::Note => {
+                    let spelling = spelling_of(note)
+                        .filter(|_| invocation.notation_strict);
+                    guess_note(&mut guess_input, note, spelling, tone, previous,
+                               rules, &mut session.guess_stats)
+                },
+                Mode::Octave => {
+                    let octave = octave_of(note, spelling_of(note));
+                    guess_octave(&mut guess_input, note, octave, tone)
+                },
+                Mode::Frequency
+                    => guess_frequency(&mut guess_input, note, played_freq,
+                                       invocation.freq_tolerance, tone),
+                Mode::Detune
+                    => guess_detune(&mut guess_input, detuned_freq, detune,
+                                    invocation.detune_tolerance, tone),
+                Mode::TheoryInterval
+                    => guess_interval(&mut guess_input, notes[0].abs_diff(notes[1]),
+                                      &full_note_name(notes[0]),
+                                      &full_note_name(notes[1])),
+                Mode::Interval
+                    => guess_played_interval(&mut guess_input, &notes, tone),
+                Mode::IntervalQuality
+                    => guess_interval_quality(&mut guess_input, &notes, tone),
+                Mode::Melody
+                    => guess_melody(&mut guess_input, &notes,
+                                    invocation.ignore_octave,
+                                    invocation.metronome,
+                                    invocation.portamento, tone),
+                Mode::Sing => {
+                    let path = invocation.record_attempts.as_ref().map(|dir| {
+                        dir.join(format!("round-{:02}-{}-{}.wav",
+                                         session.rounds_played + 1,
+                                         full_note_name(note),
+                                         attempt_limit - rem_guesses))
+                    });
+                    guess_sing(&mut guess_input, note, invocation.tolerance_cents,
+                               path.as_deref())
+                },
+                Mode::Inversion => {
+                    let chord = round.chord.unwrap();
+                    let freqs = freqs(&notes, chord.root,
+                                      invocation.temperament);
+                    guess_inversion(&mut guess_input, chord.inversion, &freqs,
+                                    invocation.arpeggiate, tone)
+                },
+                Mode::ChordQuality => {
+                    let chord = round.chord.unwrap();
+                    let freqs = freqs(&notes, chord.root,
+                                      invocation.temperament);
+                    guess_chord_quality(&mut guess_input, chord.quality, &freqs,
+                                        invocation.arpeggiate, tone)
+                },
+                Mode::CountNotes
+                    => guess_count(&mut guess_input, notes.len(), &cluster_freqs,
+                                   tone),
+                Mode::Duel | Mode::Learn | Mode::Progression | Mode::Mixed
+                    => unreachable!(),
+            };
+            response_time.get_or_insert_with(|| start.elapsed());
+            let lives_left = invocation.lives.map(|lives| {
+                if matches!(guess, Guess::Wrong(_) | Guess::Misspelled(_)) {
+                    session.lives_lost += 1;
+                }
+                lives.saturating_sub(session.lives_lost)
+            });
+            out_of_lives = lives_left == Some(0);
+            if mode == Mode::Melody && matches!(guess, Guess::Wrong(_)) {
+                let right = parse_melody(input.last_line(),
+                                         invocation.ignore_octave)
+                    .map_or(0, |guesses| {
+                        melody_notes_right(&guesses, &notes,
+                                           invocation.ignore_octave)
+                    });
+                if right > 0 && !invocation.blind {
+                    say!(Essential, "{} of the {} notes {} in the right place.",
+                         right, notes.len(),
+                         if right == 1 { "was" } else { "were" });
+                }
+                melody_credit = melody_credit.max(right);
+            }
+            if mode == Mode::Note {
+                let played = (note % NOTES_PER_OCTAVE) as usize;
+                match guess {
+                    Guess::Wrong(Some(guess)) => {
+                        session.confusion[played][guess.pitch_class as usize]
+                            += 1;
+                        guesses.push(guess.pitch_class);
+                    },
+                    Guess::Perfect | Guess::WrongOctave(_) => {
+                        session.confusion[played][played] += 1;
+                        guesses.push(played as u32);
+                    },
+                    _ => (),
+                }
+            }
+            if invocation.blind {
+                let guessed = input.last_line().trim().to_string();
+                let result = match guess {
+                    Guess::Wrong(_) => "wrong",
+                    Guess::Misspelled(_) => "misspelled",
+                    Guess::WrongOctave(_) => "wrong octave",
+                    Guess::Perfect => "right",
+                    Guess::Hedged => "hedged",
+                    Guess::Skipped => "skipped",
+                    Guess::Quit => break 'session,
+                };
+                session.answer_key.push((answer.clone(), guessed,
+                                         result.to_string()));
+            }
+            match guess {
+                Guess::Wrong(_) | Guess::Misspelled(_) if invocation.blind => {
+                    if out_of_lives { break }
+                },
+                Guess::Wrong(_) | Guess::Misspelled(_)
+                    if feedback == Feedback::None
+                    && rem_guesses > 0 && !out_of_lives => (),
+                Guess::Wrong(_) | Guess::Misspelled(_) => {
+                    if let Guess::Misspelled(spelling) = guess {
+                        say!(Essential, "That's the right note, but here it's \
+                                         spelled {}.", spelling.name());
+                    }
+                    match &guess {
+                        Guess::Wrong(Some(guessed))
+                            if feedback == Feedback::Full => {
+                            say!(Essential, "{}", distance_hint(
+                                guessed, note, invocation.ignore_octave));
+                        },
+                        _ => (),
+                    }
+                    if let Some(lives) = lives_left.filter(|&x| x > 0) {
+                        say!(Essential, "{} {} left.", lives,
+                             if lives == 1 { "life" } else { "lives" });
+                    }
+                    if rem_guesses > 1 && !out_of_lives {
+                        say!(Essential, "Try again ({} guesses left)",
+                             rem_guesses);
+                    }
+                    else if rem_guesses > 0 && !out_of_lives {
+                        say!(Essential, "Try again (last guess)");
+                    }
+                    else {
+                        if out_of_lives { say!(Essential, "Out of lives!") }
+                        else { say!(Essential, "Out of guesses.") }
+                        say!(Essential, "The {} was: {}", answer_kind, answer);
+                        if invocation.staff && mode == Mode::Note {
+                            say!(Essential, "{}", staff::render_staff(
+                                note, staff_spelling(note), true));
+                        }
+                        if invocation.feedback_sounds { play_failure_sound() }
+                        if invocation.reinforce_miss
+                            && mode == Mode::Note {
+                            play_note(note, tone);
+                            speak(&names[0]);
+                        }
+                        break
+                    }
+                },
+                Guess::WrongOctave(guessed) if invocation.blind => {
+                    session.octave_distances
+                        .push(octave_distance(guessed, note));
+                    session.right_count += 1;
+                    outcome = Outcome::WrongOctave;
+                    break
+                },
+                Guess::WrongOctave(guessed) => {
+                    if notes.len() > 1 {
+                        say!(Essential, "You got the notes right, but the \
+                                         octaves wrong.");
+                    }
+                    else {
+                        say!(Essential, "You got the note right, but the \
+                                         octave wrong.");
+                    }
+                    say!(Essential, "The correct answer was: {}", answer);
+                    if let Some(guessed) = guessed
+                        .filter(|_| invocation.reinforce_octave) {
+                        say!(Essential, "Here it is in the octave you \
+                                         guessed, {}:",
+                             full_note_name(guessed));
+                        play_note(guessed, tone);
+                    }
+                    session.octave_distances
+                        .push(octave_distance(guessed, note));
+                    session.right_count += 1;
+                    outcome = Outcome::WrongOctave;
+                    break
+                },
+                Guess::Perfect => {
+                    if !invocation.blind {
+                        say!(Essential, "Correct!");
+                        if invocation.feedback_sounds { play_success_sound() }
+                    }
+                    session.perfect_count += 1;
+                    stats.perfect += 1;
+                    outcome = Outcome::Perfect;
+                    break
+                },
+                Guess::Hedged => {
+                    if !invocation.blind {
+                        say!(Essential, "One of those was right: {}. Half \
+                                         credit for hedging.", answer);
+                    }
+                    session.hedged_count += 1;
+                    outcome = Outcome::Hedged;
+                    break
+                },
+                Guess::Skipped => {
+                    if !invocation.blind {
+                        say!(Essential, "Skipped. The {} was: {}", answer_kind,
+                             answer);
+                    }
+                    session.skipped_count += 1;
+                    break
+                },
+                Guess::Quit => {
+                    say!(Essential);
+                    break 'session
+                },
+            }
+        }
+        if !invocation.blind {
+            let freqs: Vec<String> = match round.chord {
+                Some(chord) => freqs(&notes, chord.root,
+                                     invocation.temperament),
+                None if mode == Mode::Detune => vec![detuned_freq],
+                None => notes.iter().map(|&note| note_freq(note)).collect(),
+            }.iter().map(|&freq| format!("{:.1}Hz", tone.sounding(freq)))
+                .collect();
+            say!(Verbose, "Frequencies: {}. Answered in {:.2}s.",
+                 freqs.join(" "),
+                 response_time.unwrap_or_default().as_secs_f32());
+        }
+        session.boosts.retain_mut(|boost: &mut Boost| {
+            boost.rounds_left -= 1;
+            boost.rounds_left > 0
+        });
+        if outcome == Outcome::Missed && invocation.neighbor_drill {
+            for neighbor in [note.saturating_sub(2), note.saturating_sub(1),
+                             note + 1, note + 2] {
+                if neighbor < min_note || neighbor > max_note { continue }
+                session.boosts.retain(|boost| boost.note != neighbor);
+                session.boosts.push(Boost { note: neighbor,
+                                    rounds_left: NEIGHBOR_DRILL_ROUNDS });
+            }
+        }
+        if invocation.keyboard && notes.len() == 1 && !invocation.blind {
+            say!(Essential, "{}", keyboard::render_keyboard(note));
+        }
+        if outcome == Outcome::Missed { session.melody_credit += melody_credit }
+        stats.rounds += 1;
+        stats.response_time += response_time.unwrap_or_default();
+        session.round_records.push(history::RoundRecord { notes, outcome,
+                                                          reflection,
+                                                          guesses });
+        session.rounds_played += 1;
+        if invocation.blind {
+            events.send(&format!("result {} {}", session.rounds_played,
+                                 events::outcome_name(outcome)));
+        }
+        else {
+            events.send(&format!("result {} {} {}", session.rounds_played,
+                                 events::outcome_name(outcome), answer));
+        }
+        if invocation.running_score && !invocation.blind {
+            let score = session.score(invocation.weights(),
+                                      invocation.octave_distance_credit,
+                                      invocation.melody_length);
+            if deadline.is_some() || invocation.until_correct.is_some() {
+                say!(Essential, "Score so far: {}% = {} (round {})", score,
+                     grade_for(score), session.rounds_played);
+            }
+            else {
+                say!(Essential, "Score so far: {}% = {} (round {} of {})",
+                     score, grade_for(score), session.rounds_played,
+                     invocation.test_count);
+            }
+        }
+        if out_of_lives { break }
+        // A timed session can't be picked back up (the clock's gone), and
+        // neither can sudden death.
+        if deadline.is_some() || sudden_death.is_some() { continue }
+        let Some(path) = checkpoint_path.as_ref() else { continue };
+        let checkpoint = Checkpoint {
+            mode: invocation.mode,
+            min_note, max_note,
+            test_count: invocation.test_count,
+            seed: rng.get_seed(),
+            word_pos: rng.get_word_pos(),
+            spelling_word_pos: spelling_rng.get_word_pos(),
+            jitter_word_pos: jitter_rng.get_word_pos(),
+            session: session.clone(),
+        };
+        match checkpoint::save(path, &checkpoint) {
+            Ok(()) => checkpointed = true,
+            Err(x) => eprintln!("Couldn't save a checkpoint in {:?}: {}",
+                                path, x),
+        }
+    }
+    if let Some(path) = checkpoint_path.as_ref().filter(|_| checkpointed) {
+        if let Err(x) = checkpoint::remove(path) {
+            eprintln!("Couldn't remove {:?}: {}", path, x);
+        }
+    }
+    let sudden_death = sudden_death.map(|(target, before)| {
+        let won = session.round_records.len() > before.round_records.len()
+            && session.round_records.last().unwrap().outcome
+                == Outcome::Perfect;
+        session = before;
+        (target, won)
+    });
+    if session.rounds_played == 0 {
+        say!(Essential, "No rounds completed.");
+        return
+    }
+    if invocation.blind {
+        say!(Essential, "Answer key:");
+        for (index, (answer, guessed, result)) in session.answer_key.iter()
+            .enumerate() {
+            say!(Essential, "{:>3}. {}: you said {:?}, {}", index + 1, answer,
+                 guessed, result);
+        }
+    }
+    if let Some(lives) = invocation.lives {
+        if session.lives_lost >= lives {
+            say!(Essential, "You ran out of lives after {} rounds.",
+                 session.rounds_played);
+        }
+        else {
+            say!(Essential, "You finished with {} of {} lives left.",
+                 lives - session.lives_lost, lives);
+        }
+    }
+    if let Some(goal) = invocation.until_correct {
+        if session.perfect_count >= goal {
+            say!(Essential, "It took you {} rounds to get {} right.",
+                 session.rounds_played, goal);
+        }
+        else {
+            say!(Essential, "You got {} of {} right in {} {}.",
+                 session.perfect_count, goal, session.rounds_played,
+                 if session.rounds_played == 1 { "round" } else { "rounds" });
+        }
+    }
+    if deadline.is_some() {
+        say!(Essential, "You played {} rounds in {:.1} minutes.",
+             session.rounds_played,
+             session_start.elapsed().as_secs_f32() / 60.0);
+    }
+    let score = if invocation.octave_distance_credit {
+        let distances = &session.octave_distances;
+        let count = |range: std::ops::RangeInclusive<u32>| distances.iter()
+            .filter(|x| range.contains(x)).count();
+        say!(Essential, "You got {}/{} correct. {} wrong-octave guesses were \
+                         one octave off (3/4 credit), {} two (1/2), {} three \
+                         (1/4), and {} more (none).",
+             session.perfect_count, session.rounds_played, count(1 ..= 1),
+             count(2 ..= 2), count(3 ..= 3), count(4 ..= u32::MAX));
+        if session.hedged_count > 0 {
+            say!(Essential, "{} credit for {} hedged guesses.",
+                 invocation.weights().credit(invocation.weights().hedge),
+                 session.hedged_count);
+        }
+        session.score(invocation.weights(), true, invocation.melody_length)
+    }
+    else {
+        let score = print_tally(invocation.weights(), session.perfect_count,
+                                session.right_count, session.hedged_count,
+                                session.rounds_played);
+        print_melody_credit(session.melody_credit, invocation.melody_length);
+        if session.melody_credit > 0 {
+            session.score(invocation.weights(), false,
+                          invocation.melody_length)
+        }
+        else { score }
+    };
+    let score = if invocation.difficulty_weighted {
+        let weighted = compute_weighted_score(invocation.weights(),
+                                              &session.round_records);
+        say!(Essential, "Without weighting by difficulty, that's {}% = {}.",
+             score, grade_for(score));
+        weighted
+    }
+    else { score };
+    let score = match sudden_death {
+        Some((target, true)) => {
+            say!(Essential, "You won the sudden-death round, for {}%.",
+                 target);
+            target
+        },
+        Some((_, false)) => {
+            say!(Essential, "You lost the sudden-death round, so your score \
+                             stands.");
+            score
+        },
+        None => score,
+    };
+    say!(Essential, "Your final score: {}% = {}, {} compares, {} confirms, \
+                     offness total {}",
+         score, grade_for(score), session.guess_stats.compares,
+         session.guess_stats.confirms, session.guess_stats.offness_total);
+    let pitch_classes: Vec<u32> = match spellings.as_ref() {
+        Some(spellings) => {
+            let mut pitch_classes: Vec<u32> = spellings.iter()
+                .map(|x| x.pitch_class()).collect();
+            pitch_classes.sort_unstable();
+            pitch_classes.dedup();
+            pitch_classes
+        },
+        None => (0 .. NOTES_PER_OCTAVE).collect(),
+    };
+    if invocation.reflect {
+        say!(Essential, "What you heard:");
+        for (index, record) in session.round_records.iter().enumerate() {
+            let names: Vec<String> = record.notes.iter()
+                .map(|&note| full_note_name(note)).collect();
+            say!(Essential, "{:>3}. {}: {}", index + 1, names.join(" "),
+                 record.reflection.as_deref().unwrap_or("(nothing)"));
+        }
+    }
+    if invocation.mode == Mode::Mixed {
+        print_mixed_breakdown(invocation.weights(), &session.round_records);
+    }
+    say!(Essential, "Difficulty: {}", difficulty(&invocation, min_note,
+                                                max_note, &pitch_classes));
+    if tuning::base_freq() != BASE_FREQ {
+        say!(Essential, "Tuning: {} = {}Hz", full_note_name(BASE_NOTE as u32),
+             tuning::base_freq());
+    }
+    events.send(&format!("done {}", score));
+    if session.skipped_count > 0 {
+        say!(Essential, "You skipped {} of {} rounds.", session.skipped_count,
+             session.rounds_played);
+    }
+    if invocation.breakdown && invocation.mode == Mode::Note {
+        print_breakdown(&session.breakdown);
+    }
+    if invocation.mode == Mode::Note {
+        if invocation.confusion {
+            let _ = print_confusion(&mut stdout(), &session.confusion);
+        }
+        if let Some(path) = invocation.confusion_file.as_ref() {
+            if let Err(x) = write_confusion(path, &session.confusion) {
+                eprintln!("Couldn't write {:?}: {}", path, x);
+            }
+        }
+    }
+    if let (Some(schedule), Some(path)) = (schedule.as_mut(),
+                                           schedule_path.as_ref()) {
+        schedule.review(&session.round_records, history::now());
+        if let Err(x) = schedule.save(path) {
+            eprintln!("Couldn't save the schedule to {:?}: {}", path, x);
+        }
+    }
+    let record = history::SessionRecord {
+        time: history::now(),
+        mode: invocation.mode,
+        min_note, max_note,
+        rounds: session.rounds_played,
+        perfect: session.perfect_count,
+        wrong_octave: session.right_count,
+        hedged: session.hedged_count,
+        score,
+        round_records: session.round_records,
+    };
+    if let Some(path) = invocation.db.as_ref() {
+        if let Err(x) = database::record(path, &record, invocation.db_rounds) {
+            eprintln!("Couldn't record this session in {:?}: {}", path, x);
+        }
+    }
+    if let Some(path) = history_path.filter(|_| !invocation.no_history) {
+        match history::load(&path) {
+            Ok(past) => {
+                say!(Essential, "{}", history::comparison(&past, &record));
+            },
+            Err(x) => eprintln!("Couldn't read {:?}: {}", path, x),
+        }
+        if let Err(x) = history::append(&path, &record) {
+            eprintln!("Couldn't record this session in {:?}: {}", path, x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invocation(args: &[&str]) -> Invocation {
+        Invocation::parse_from(std::iter::once("what-note")
+                               .chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn assessment() {
+        let _lock = naming::test_lock();
+        let invocation = invocation(&["-t", "4"]);
+        let (min_note, max_note) = (MIDDLE_C, MIDDLE_C + NOTES_PER_OCTAVE);
+        let rng = ChaCha12Rng::seed_from_u64(3);
+        let notes: Vec<u32> = (0 .. 4).scan(rng.clone(), |rng, _| {
+            Some(draw_round(invocation.mode, rng, min_note, max_note, &[],
+                            None, None, invocation.round_shape()).notes[0])
+        }).collect();
+        // Two right, one in the wrong octave, and one wrong.
+        let answers = [full_note_name(notes[0]), full_note_name(notes[1]),
+                       full_note_name(notes[2] + NOTES_PER_OCTAVE),
+                       full_note_name(notes[3] + 1)];
+        let answers: Vec<&str> = answers.iter().map(String::as_str).collect();
+        assert_eq!(assess(&invocation, &answers, &mut rng.clone(), min_note,
+                          max_note, None, None), Some(63));
+        assert_eq!(assess(&invocation, &[], &mut rng.clone(), min_note,
+                          max_note, None, None), None);
+    }
+
+    #[test]
+    fn end_of_input() {
+        let mut input = std::io::Cursor::new("C4\r\n\nE4");
+        assert_eq!(read_input(&mut input).as_deref(), Some("C4"));
+        assert_eq!(read_input(&mut input).as_deref(), Some(""));
+        assert_eq!(read_input(&mut input).as_deref(), Some("E4"));
+        assert_eq!(read_input(&mut input), None);
+        assert_eq!(read_input(&mut input), None);
+        // At the end of input, a round is a quit, not a wrong answer.
+        let tone = Tone { duration: 0.0, waveform: Waveform::Sine,
+                          antialias: false, transpose: 0,
+                          out_of_range: OutOfRange::Clamp };
+        let mut prompt = vec![];
+        let mut input = GuessInput { lines: &mut std::io::Cursor::new(""),
+                                     prompt: &mut prompt };
+        assert!(matches!(guess_octave(&mut input, MIDDLE_C, 3, tone),
+                         Guess::Quit));
+        assert!(matches!(guess_count(&mut input, 3, &[], tone), Guess::Quit));
+        assert_eq!(prompt, b"Your guess?\nYour guess?\n");
+    }
+
+    #[test]
+    fn score_bounds() {
+        let max = Weights { perfect: u32::MAX, octave: u32::MAX,
+                            hedge: u32::MAX };
+        assert_eq!(compute_score(max, u32::MAX, 0, 0, u32::MAX), 100);
+        assert_eq!(compute_score(max, 0, u32::MAX, 0, u32::MAX), 100);
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, u32::MAX, 0, 0, u32::MAX),
+                   100);
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, 0, 0, 0, u32::MAX), 0);
+        assert_eq!(compute_melody_score(max, u32::MAX, 0, 0, 0,
+                                        MAX_MELODY_LENGTH, u32::MAX), 100);
+    }
+
+    #[test]
+    fn score_rounding() {
+        // 12.5% and 37.5% round up.
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, 0, 1, 0, 4), 13);
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, 1, 1, 0, 4), 38);
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, 0, 0, 0, 0), 0);
+        assert_eq!(compute_score(DEFAULT_WEIGHTS, 3, 0, 1, 4), 88);
+    }
+
+    #[test]
+    fn distance_score() {
+        let _lock = naming::test_lock();
+        let c = MIDDLE_C;
+        assert_eq!(octave_distance(Some(c + NOTES_PER_OCTAVE), c), 1);
+        assert_eq!(octave_distance(Some(c - NOTES_PER_OCTAVE), c), 1);
+        assert_eq!(octave_distance(Some(c + 3 * NOTES_PER_OCTAVE), c), 3);
+        assert_eq!(octave_distance(None, c), 1);
+        // Right on is a perfect answer, with no distance to it.
+        assert_eq!(compute_distance_score(DEFAULT_WEIGHTS, 1, &[], 0, 1), 100);
+        // One octave off is 3/4 credit, two 1/2, three 1/4, more nothing.
+        let score = |distance| {
+            compute_distance_score(DEFAULT_WEIGHTS, 0, &[distance], 0, 1)
+        };
+        assert_eq!([1, 2, 3, 4, 7].map(score), [75, 50, 25, 0, 0]);
+        assert_eq!(compute_distance_score(DEFAULT_WEIGHTS, 1, &[1, 3], 0, 4),
+                   50);
+        // With the octave ignored, the same guesses are all perfect.
+        let invocation = invocation(&["--ignore-octave"]);
+        let guess = parse_guess("C", invocation.ignore_octave).unwrap();
+        assert!(matches!(judge_note(&guess, c + 2 * NOTES_PER_OCTAVE, true),
+                         Guess::Perfect));
+        assert_eq!(compute_distance_score(DEFAULT_WEIGHTS, 3, &[], 0, 3), 100);
+    }
+
+    #[test]
+    fn guesses_with_and_without_octaves() {
+        let _lock = naming::test_lock();
+        let c4 = MIDDLE_C + NOTES_PER_OCTAVE;
+        // Normal mode needs the octave, and checks it.
+        assert!(parse_guess("C", false).is_none());
+        let guess = parse_guess("C4", false).unwrap();
+        assert!(matches!(judge_note(&guess, c4, false), Guess::Perfect));
+        assert!(matches!(judge_note(&guess, MIDDLE_C, false),
+                         Guess::WrongOctave(Some(x)) if x == c4));
+        assert!(matches!(judge_note(&guess, c4 + 1, false),
+                         Guess::Wrong(Some(_))));
+        // Ignoring the octave, it can be left off, or given out of habit,
+        // and either way only the pitch class counts.
+        for name in ["C", "C4", "C1"] {
+            let guess = parse_guess(name, true).unwrap();
+            assert!(matches!(judge_note(&guess, c4, true), Guess::Perfect));
+            assert!(matches!(judge_note(&guess, MIDDLE_C, true),
+                             Guess::Perfect));
+            assert!(matches!(judge_note(&guess, c4 + 1, true),
+                             Guess::Wrong(Some(_))));
+        }
+        assert!(parse_guess("H", true).is_none());
+        assert!(parse_guess("4", true).is_none());
+        // Accidentals, in ASCII or not, doubled or not. Cb and B# belong to
+        // the octave of their letter, not of the note they sound as.
+        let note = |name| parse_guess(name, false).and_then(|x| x.note());
+        let b3 = MIDDLE_C + 11;
+        for (name, expected) in [("Db4", c4 + 1), ("D♭4", c4 + 1),
+                                 ("C♯4", c4 + 1), ("Ebb4", c4 + 2),
+                                 ("E𝄫4", c4 + 2), ("C##4", c4 + 2),
+                                 ("F𝄪3", MIDDLE_C + 7), ("Cb4", b3),
+                                 ("B#3", c4), ("B♯3", c4)] {
+            assert_eq!(note(name), Some(expected), "{}", name);
+        }
+        let guess = parse_guess("Cb4", false).unwrap();
+        assert!(matches!(judge_note(&guess, b3, false), Guess::Perfect));
+        let guess = parse_guess("F𝄪3", false).unwrap();
+        assert!(matches!(judge_note(&guess, MIDDLE_C + 7, false),
+                         Guess::Perfect));
+        let guess = parse_guess("Cb", true).unwrap();
+        assert!(matches!(judge_note(&guess, b3, true), Guess::Perfect));
+    }
+
+    #[test]
+    fn help_follows_octave_convention() {
+        let _lock = naming::test_lock();
+        let help = |convention| {
+            naming::set_octave_convention(convention);
+            let command = convention_help(Invocation::command());
+            naming::set_octave_convention(OctaveConvention::C3);
+            let help = |id| command.get_arguments()
+                .find(|arg| arg.get_id() == id).unwrap()
+                .get_help().unwrap().to_string();
+            [help("low"), help("allow-hedge"), help("from-chord")]
+        };
+        let [low, hedge, chord] = help(OctaveConvention::C3);
+        assert!(low.contains("\"C2\""));
+        assert!(hedge.contains("\"C#4,D4\""));
+        assert!(chord.contains("\"C7\""));
+        let [low, hedge, chord] = help(OctaveConvention::C4);
+        assert!(low.contains("\"C3\""));
+        assert!(hedge.contains("\"C#5,D5\""));
+        assert!(chord.contains("\"C7\""));
+        let [low, hedge, _] = help(OctaveConvention::Helmholtz);
+        assert!(low.contains("\"c\""));
+        assert!(hedge.contains("\"c#'',d''\""));
+    }
+
+    /// The notes of a melody, if it parses and every note has an octave.
+    fn melody(guess: &str, ignore_octave: bool) -> Option<Vec<Option<u32>>> {
+        parse_melody(guess, ignore_octave)
+            .map(|notes| notes.iter().map(ParsedNote::note).collect())
+    }
+
+    #[test]
+    fn melodies() {
+        let _lock = naming::test_lock();
+        let c4 = MIDDLE_C + NOTES_PER_OCTAVE;
+        let ceg = Some(vec![Some(c4), Some(c4 + 4), Some(c4 + 7)]);
+        assert_eq!(melody("C4E4G4", false), ceg);
+        assert_eq!(melody("C4 E4 G4", false), ceg);
+        assert_eq!(melody("  C4\tE4G4 ", false), ceg);
+        assert_eq!(melody("C#4Db4", false),
+                   Some(vec![Some(c4 + 1), Some(c4 + 1)]));
+        assert_eq!(melody("Bb3B#3", false),
+                   Some(vec![Some(c4 - 2), Some(c4)]));
+        // Without octaves, only if they're being ignored.
+        assert_eq!(melody("CEG", true), Some(vec![None, None, None]));
+        assert_eq!(melody("CEG", false), None);
+        assert_eq!(melody("C4E4G", false), None);
+        // Trailing junk, junk in between, and nothing at all.
+        assert_eq!(melody("C4E4G4x", false), None);
+        assert_eq!(melody("C4E4G4 ?", false), None);
+        assert_eq!(melody("C4 x E4", false), None);
+        assert_eq!(melody("c4e4", false), None);
+        assert_eq!(melody("4C4", false), None);
+        assert_eq!(melody("", false), None);
+        assert_eq!(melody("   ", false), None);
+    }
+
+    fn record(note: u32, outcome: Outcome) -> history::RoundRecord {
+        history::RoundRecord { notes: vec![note], outcome, reflection: None,
+                               guesses: vec![] }
+    }
+
+    #[test]
+    fn difficulty_ordering() {
+        let c = MIDDLE_C;
+        // White keys near middle C are easiest, then black keys, then notes
+        // further away.
+        assert_eq!(note_difficulty(c), 1);
+        assert_eq!(note_difficulty(c + 11), 1);
+        assert!(note_difficulty(c + 1) > note_difficulty(c));
+        assert!(note_difficulty(c + NOTES_PER_OCTAVE) > note_difficulty(c));
+        assert!(note_difficulty(c - NOTES_PER_OCTAVE) > note_difficulty(c));
+        assert!(note_difficulty(c + 2 * NOTES_PER_OCTAVE)
+                > note_difficulty(c + NOTES_PER_OCTAVE));
+        assert!(note_difficulty(c + NOTES_PER_OCTAVE + 1)
+                > note_difficulty(c + 1));
+    }
+
+    #[test]
+    fn weighted_score() {
+        let easy = MIDDLE_C;
+        let hard = MIDDLE_C + NOTES_PER_OCTAVE + 1;
+        assert_eq!(note_difficulty(hard), 3);
+        assert_eq!(compute_weighted_score(DEFAULT_WEIGHTS, &[]), 0);
+        // Getting the hard note is worth three times the easy one.
+        assert_eq!(compute_weighted_score(DEFAULT_WEIGHTS, &[
+            record(easy, Outcome::Perfect), record(hard, Outcome::Missed),
+        ]), 25);
+        assert_eq!(compute_weighted_score(DEFAULT_WEIGHTS, &[
+            record(easy, Outcome::Missed), record(hard, Outcome::Perfect),
+        ]), 75);
+        assert_eq!(compute_weighted_score(DEFAULT_WEIGHTS, &[
+            record(easy, Outcome::Perfect), record(hard, Outcome::WrongOctave),
+        ]), 63);
+        // A round counts as much as its hardest note.
+        let chord = history::RoundRecord { notes: vec![easy, hard],
+                                           ..record(easy, Outcome::Perfect) };
+        assert_eq!(compute_weighted_score(DEFAULT_WEIGHTS, &[
+            chord, record(easy, Outcome::Missed),
+        ]), 75);
+    }
 }
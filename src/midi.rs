@@ -0,0 +1,208 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, mpsc::{Receiver, Sender, channel}},
+    thread::sleep,
+    time::Duration,
+};
+use once_cell::sync::OnceCell;
+
+use crate::{HIGHEST_NOTE, LOWEST_NOTE, Tone, full_note_name};
+
+/// How hard each note is struck.
+const VELOCITY: u8 = 96;
+const NOTE_ON: u8 = 0x90;
+const NOTE_OFF: u8 = 0x80;
+
+/// The raw MIDI device notes go to, for `--midi-out`.
+static DEVICE: OnceCell<Mutex<File>> = OnceCell::new();
+
+/// The raw MIDI devices there are to choose from: `/dev/snd/midi*` (ALSA)
+/// and `/dev/midi*` (OSS).
+pub fn list() -> Vec<PathBuf> {
+    let mut devices = vec![];
+    for dir in ["/dev/snd", "/dev"] {
+        let Ok(entries) = std::fs::read_dir(dir) else { continue };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with("midi") {
+                devices.push(entry.path());
+            }
+        }
+    }
+    devices.sort();
+    devices
+}
+
+/// Opens the device at `path`. From then on, `play` sends notes there
+/// instead of making a sound.
+pub fn open(path: &Path) -> Result<(), String> {
+    let file = OpenOptions::new().write(true).open(path)
+        .map_err(|x| format!("Couldn't open {:?}: {}", path, x))?;
+    let _ = DEVICE.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Plays `notes` at once on the MIDI device, and waits for them to finish.
+/// Returns `false` (having done nothing) if there's no MIDI device in use.
+pub fn play(notes: &[u32], tone: Tone) -> bool {
+    let Some(device) = DEVICE.get() else { return false };
+    let mut device = device.lock().unwrap();
+    // What's stored as note numbers is already MIDI's numbering.
+    let keys: Vec<u8> = notes.iter()
+        .map(|&note| (note as i32 + tone.transpose).clamp(0, 127) as u8)
+        .collect();
+    let send = |device: &mut File, status: u8, velocity: u8| {
+        let message: Vec<u8> = keys.iter()
+            .flat_map(|&key| [status, key, velocity]).collect();
+        let sent = device.write_all(&message).and_then(|_| device.flush());
+        if let Err(x) = sent {
+            eprintln!("Couldn't send a note to the MIDI device: {}", x);
+            std::process::exit(1)
+        }
+    };
+    send(&mut device, NOTE_ON, VELOCITY);
+    sleep(Duration::from_secs_f32(tone.duration));
+    send(&mut device, NOTE_OFF, 0);
+    true
+}
+
+/// Guesses from both the keyboard you type on and a MIDI keyboard, for
+/// `--midi-in`. Each key pressed comes through as a line naming its note, as
+/// if it had been typed.
+pub struct Input {
+    lines: Receiver<Option<String>>,
+    line: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+/// Starts reading guesses from `typed` and from the raw MIDI device at
+/// `path`. A key `transpose` semitones up from a note names that note, so
+/// that it's the key that sounds like what was played.
+pub fn input(path: &Path, typed: Box<dyn BufRead + Send>, transpose: i32)
+             -> Result<Input, String> {
+    let device = File::open(path)
+        .map_err(|x| format!("Couldn't open {:?}: {}", path, x))?;
+    let (sender, lines) = channel();
+    let midi_sender = sender.clone();
+    std::thread::spawn(move || read_typed(typed, sender));
+    std::thread::spawn(move || read_midi(device, midi_sender, transpose));
+    Ok(Input { lines, line: vec![], pos: 0, done: false })
+}
+
+/// Sends each line of `typed`, then `None` at the end.
+fn read_typed(typed: Box<dyn BufRead + Send>, sender: Sender<Option<String>>) {
+    for line in typed.lines() {
+        let Ok(line) = line else { break };
+        if sender.send(Some(line + "\n")).is_err() { return }
+    }
+    let _ = sender.send(None);
+}
+
+/// Picks the keys pressed out of a stream of MIDI bytes.
+#[derive(Default)]
+struct Parser {
+    /// MIDI lets messages leave out the status byte if it's the same as the
+    /// last one's.
+    status: u8,
+    data: Vec<u8>,
+}
+
+impl Parser {
+    /// Takes the next byte, and returns the key if it finishes a note-on.
+    fn feed(&mut self, byte: u8) -> Option<u8> {
+        match byte {
+            // Real-time messages can come in the middle of anything.
+            0xF8 ..= 0xFF => return None,
+            0x80 ..= 0xF7 => {
+                // System messages cancel running status.
+                self.status = if byte < 0xF0 { byte } else { 0 };
+                self.data.clear();
+                return None
+            },
+            _ => self.data.push(byte),
+        }
+        // Program changes and channel pressure have one data byte; the other
+        // channel messages have two.
+        let length = match self.status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        if self.data.len() < length { return None }
+        let message = std::mem::take(&mut self.data);
+        // A note-on with no velocity is really a note-off.
+        if self.status & 0xF0 != NOTE_ON || message[1] == 0 { return None }
+        Some(message[0])
+    }
+}
+
+/// Sends the name of the note of each key pressed on `device`. Keys for notes
+/// that can't be named are left out.
+fn read_midi(device: File, sender: Sender<Option<String>>, transpose: i32) {
+    let mut parser = Parser::default();
+    for byte in BufReader::new(device).bytes() {
+        let Ok(byte) = byte else { break };
+        let Some(key) = parser.feed(byte) else { continue };
+        let note = key as i32 - transpose;
+        if note < LOWEST_NOTE as i32 || note > HIGHEST_NOTE as i32 { continue }
+        let line = full_note_name(note as u32) + "\n";
+        if sender.send(Some(line)).is_err() { return }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+        buf[.. amount].copy_from_slice(&available[.. amount]);
+        self.consume(amount);
+        Ok(amount)
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos >= self.line.len() && !self.done {
+            match self.lines.recv() {
+                Ok(Some(line)) => {
+                    self.line = line.into_bytes();
+                    self.pos = 0;
+                },
+                Ok(None) | Err(_) => self.done = true,
+            }
+        }
+        if self.done { return Ok(&[]) }
+        Ok(&self.line[self.pos ..])
+    }
+    fn consume(&mut self, amount: usize) { self.pos += amount }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<u8> {
+        let mut parser = Parser::default();
+        bytes.iter().filter_map(|&byte| parser.feed(byte)).collect()
+    }
+
+    #[test]
+    fn note_ons() {
+        assert_eq!(keys(&[0x90, 60, 100, 0x80, 60, 0]), [60]);
+        // Running status, with a note-on of velocity 0 as a note-off.
+        assert_eq!(keys(&[0x91, 60, 100, 60, 0, 64, 90]), [60, 64]);
+        // A clock tick in the middle of a message.
+        assert_eq!(keys(&[0x90, 60, 0xF8, 100]), [60]);
+    }
+
+    #[test]
+    fn one_data_byte() {
+        // Program changes under running status, then a note-on.
+        assert_eq!(keys(&[0xC0, 5, 6, 7, 0x90, 60, 100]), [60]);
+        // Channel pressure under running status must not eat the data bytes
+        // of what follows it as a pair.
+        assert_eq!(keys(&[0xD0, 5, 0x90, 62, 100, 0xD0, 1, 2, 3,
+                          0x90, 64, 100]), [62, 64]);
+    }
+}
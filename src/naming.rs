@@ -0,0 +1,289 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(test)]
+use std::sync::{Mutex, MutexGuard};
+use clap::ArgEnum;
+
+use crate::NOTE_NAMES;
+
+/// Which names notes go by. In German, B♮ is `H`, and B♭ is `B`. Solfège is
+/// fixed-do: C is always `Do`.
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Naming { English, German, Solfege }
+
+const GERMAN_NOTE_NAMES: &[&str] = &["C","C#","D","D#","E",
+                                     "F","F#","G","G#","A",
+                                     "A#","H"];
+const SOLFEGE_NOTE_NAMES: &[&str] = &["Do","Do#","Re","Re#","Mi",
+                                      "Fa","Fa#","Sol","Sol#","La",
+                                      "La#","Si"];
+const ENGLISH_LETTERS: &[&str] = &["C","D","E","F","G","A","B"];
+const GERMAN_LETTERS: &[&str] = &["C","D","E","F","G","A","H"];
+const SOLFEGE_LETTERS: &[&str] = &["Do","Re","Mi","Fa","Sol","La","Si"];
+/// Other syllables that can be typed for a solfège letter, as (syllable,
+/// letter).
+const SOLFEGE_ALIASES: &[(&str, usize)] = &[("Ut", 0), ("So", 4), ("Ti", 6)];
+/// The letter that's a different note in German: B♮ in English, B♭ in
+/// German.
+const B: usize = 6;
+
+/// How octaves are written. `c3` calls middle C `C3`, as this program
+/// always has; `c4` calls it `C4`, as scientific pitch notation and most MIDI
+/// software do. `helmholtz` calls it `c'`: lowercase letters from the C below
+/// middle C up, with a `'` for each octave above that, and capital letters
+/// below, with a `,` for each octave below the `C` an octave lower still.
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum OctaveConvention { C3, C4, Helmholtz }
+
+/// The `Naming` in use, as its index.
+static NAMING: AtomicU8 = AtomicU8::new(0);
+/// The `OctaveConvention` in use, as its index.
+static OCTAVE_CONVENTION: AtomicU8 = AtomicU8::new(0);
+
+/// Tests that depend on the naming (or the octave convention) take this, so
+/// that the ones that change it don't get in each other's way.
+#[cfg(test)]
+pub fn test_lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|x| x.into_inner())
+}
+
+pub fn set_naming(naming: Naming) {
+    NAMING.store(naming as u8, Ordering::Relaxed);
+}
+
+fn naming() -> Naming {
+    match NAMING.load(Ordering::Relaxed) {
+        1 => Naming::German,
+        2 => Naming::Solfege,
+        _ => Naming::English,
+    }
+}
+
+pub fn set_octave_convention(convention: OctaveConvention) {
+    OCTAVE_CONVENTION.store(convention as u8, Ordering::Relaxed);
+}
+
+pub fn octave_convention() -> OctaveConvention {
+    match OCTAVE_CONVENTION.load(Ordering::Relaxed) {
+        1 => OctaveConvention::C4,
+        2 => OctaveConvention::Helmholtz,
+        _ => OctaveConvention::C3,
+    }
+}
+
+/// How much higher an octave's number is in the convention in use than in
+/// `c3`. Helmholtz doesn't number octaves, but where a number is called for
+/// (as in octave mode), it goes by `c4`.
+pub fn octave_shift() -> i32 {
+    match octave_convention() {
+        OctaveConvention::C3 => 0,
+        OctaveConvention::C4 | OctaveConvention::Helmholtz => 1,
+    }
+}
+
+/// Writes `name` (as from `spell`) in `octave`, numbered the `c3` way.
+pub fn with_octave(name: &str, octave: i32) -> String {
+    if octave_convention() != OctaveConvention::Helmholtz {
+        return format!("{}{}", name, octave + octave_shift())
+    }
+    // `c` is C3 in scientific pitch notation, and `C` is C2.
+    let octave = octave + 1;
+    if octave >= 3 {
+        let mut chars = name.chars();
+        let first = chars.next().map(|x| x.to_lowercase().to_string())
+            .unwrap_or_default();
+        format!("{}{}{}", first, chars.as_str(),
+                "'".repeat((octave - 3) as usize))
+    }
+    else {
+        format!("{}{}", name, ",".repeat((2 - octave) as usize))
+    }
+}
+
+/// The octave (numbered the `c3` way) of a Helmholtz name, from its first
+/// letter's case and the `'`s or `,`s after it.
+pub fn helmholtz_octave(name: &str, marks: &str) -> Option<i32> {
+    let lower = name.starts_with(|x: char| x.is_lowercase());
+    let mut octave = if lower { 2 } else { 1 };
+    for mark in marks.chars() {
+        match (mark, lower) {
+            ('\'' | '′', true) => octave += 1,
+            ('″', true) => octave += 2,
+            (',', false) => octave -= 1,
+            _ => return None,
+        }
+    }
+    Some(octave)
+}
+
+fn german() -> bool { naming() == Naming::German }
+
+/// The name of each pitch class, starting at C, spelled with sharps.
+pub fn note_names() -> &'static [&'static str] {
+    match naming() {
+        Naming::English => NOTE_NAMES,
+        Naming::German => GERMAN_NOTE_NAMES,
+        Naming::Solfege => SOLFEGE_NOTE_NAMES,
+    }
+}
+
+/// The natural letter names, `C D E F G A B` (or `... A H`, or `Do Re Mi Fa
+/// Sol La Si`).
+pub fn letters() -> &'static [&'static str] {
+    match naming() {
+        Naming::English => ENGLISH_LETTERS,
+        Naming::German => GERMAN_LETTERS,
+        Naming::Solfege => SOLFEGE_LETTERS,
+    }
+}
+
+/// Writes a letter (as an index into `letters()`) and accidental. German `B`
+/// already has a flat in it, so B♭ is `B` and B𝄫 is `Bb`.
+pub fn spell(letter: usize, accidental: i32) -> String {
+    if german() && letter == B && accidental < 0 {
+        return format!("B{}", "b".repeat((-accidental - 1) as usize))
+    }
+    let symbol = if accidental < 0 { "b" } else { "#" };
+    format!("{}{}", letters()[letter],
+            symbol.repeat(accidental.unsigned_abs() as usize))
+}
+
+/// Reads a letter name off the front of a spelling, returning the letter (as
+/// an index into `letters()`), how many flats come with it (one, for German
+/// `B`), and whatever's left. Case doesn't matter.
+pub fn parse_letter(name: &str) -> Option<(usize, i32, &str)> {
+    let starts_with = |prefix: &str| name.get(.. prefix.len())
+        .filter(|x| x.eq_ignore_ascii_case(prefix))
+        .map(|_| &name[prefix.len() ..]);
+    if naming() == Naming::Solfege {
+        // Longest first, so that `Sol` isn't read as `So` and an `l`.
+        let mut syllables: Vec<(&str, usize)> = SOLFEGE_LETTERS.iter()
+            .enumerate().map(|(letter, &x)| (x, letter))
+            .chain(SOLFEGE_ALIASES.iter().copied()).collect();
+        syllables.sort_by_key(|(x, _)| std::cmp::Reverse(x.len()));
+        return syllables.iter().find_map(|&(syllable, letter)| {
+            starts_with(syllable).map(|rest| (letter, 0, rest))
+        })
+    }
+    if german() {
+        if let Some(rest) = starts_with("B") { return Some((B, -1, rest)) }
+    }
+    letters().iter().enumerate().find_map(|(letter, &x)| {
+        starts_with(x).map(|rest| (letter, 0, rest))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MIDDLE_C, NOTES_PER_OCTAVE, full_note_name, parse_note,
+                spelling::Spelling};
+
+    /// Runs `f` with the German naming.
+    fn german<T>(f: impl FnOnce() -> T) -> T {
+        let _lock = test_lock();
+        set_naming(Naming::German);
+        let result = f();
+        set_naming(Naming::English);
+        result
+    }
+
+    /// Runs `f` with the octave convention `convention`.
+    fn in_convention<T>(convention: OctaveConvention, f: impl FnOnce() -> T)
+                        -> T {
+        let _lock = test_lock();
+        set_octave_convention(convention);
+        let result = f();
+        set_octave_convention(OctaveConvention::C3);
+        result
+    }
+
+    fn note(name: &str) -> Option<u32> {
+        parse_note(name).and_then(|x| x.note())
+    }
+
+    #[test]
+    fn german_b_and_h() {
+        let b3 = MIDDLE_C + NOTES_PER_OCTAVE - 1;
+        german(|| for octave in 0 .. 5 {
+            let b = b3 + octave * NOTES_PER_OCTAVE - 3 * NOTES_PER_OCTAVE;
+            // H is B, B is Bb, Hb is Bb too, and Bb is Bbb.
+            assert_eq!(note(&format!("H{}", octave)), Some(b));
+            assert_eq!(note(&format!("B{}", octave)), Some(b - 1));
+            assert_eq!(note(&format!("Hb{}", octave)), Some(b - 1));
+            assert_eq!(note(&format!("Bb{}", octave)), Some(b - 2));
+            assert_eq!(note(&format!("H#{}", octave)), Some(b + 1));
+            assert_eq!(full_note_name(b), format!("H{}", octave));
+            assert_eq!(full_note_name(b - 1), format!("A#{}", octave));
+        });
+        // And in English, B is B.
+        let _lock = test_lock();
+        assert_eq!(note("B3"), Some(b3));
+        assert_eq!(note("H3"), None);
+    }
+
+    #[test]
+    fn german_spellings_round_trip() {
+        german(|| for letter in 0 .. letters().len() {
+            for accidental in -2 ..= 2 {
+                let name = spell(letter, accidental);
+                assert_eq!(Spelling::parse_prefix(&name),
+                           Some((Spelling { letter, accidental }, "")),
+                           "{}", name);
+            }
+        });
+        german(|| {
+            assert_eq!(spell(6, 0), "H");
+            assert_eq!(spell(6, -1), "B");
+            assert_eq!(spell(6, -2), "Bb");
+            assert_eq!(spell(6, 1), "H#");
+        });
+    }
+
+    #[test]
+    fn octave_conventions_round_trip() {
+        for convention in [OctaveConvention::C3, OctaveConvention::C4,
+                           OctaveConvention::Helmholtz] {
+            in_convention(convention, || for octave in 0 ..= 8 {
+                for letter in 0 .. letters().len() {
+                    for accidental in -1 ..= 1 {
+                        let spelling = Spelling { letter, accidental };
+                        let name = with_octave(&spell(letter, accidental),
+                                               octave);
+                        // Cb and B# sound in the next octave over.
+                        let sounding = octave + spelling.semitones_above_c()
+                            .div_euclid(NOTES_PER_OCTAVE as i32);
+                        let parsed = parse_note(&name);
+                        assert!(parsed.is_some_and(|x| {
+                            x.spelling == spelling
+                                && x.octave == Some(sounding as i16)
+                        }), "{:?} {}", convention, name);
+                    }
+                }
+            });
+        }
+        let c3 = MIDDLE_C;
+        in_convention(OctaveConvention::C3, || {
+            assert_eq!(full_note_name(c3), "C3");
+            assert_eq!(note("C3"), Some(c3));
+        });
+        in_convention(OctaveConvention::C4, || {
+            assert_eq!(full_note_name(c3), "C4");
+            assert_eq!(note("C4"), Some(c3));
+            assert_eq!(note("A4"), Some(c3 + 9));
+        });
+        in_convention(OctaveConvention::Helmholtz, || {
+            for (name, expected) in [("c'", c3), ("c''", c3 + 12),
+                                     ("c", c3 - 12), ("C", c3 - 24),
+                                     ("C,", c3 - 36), ("B,", c3 - 25),
+                                     ("f#'", c3 + 6), ("a#", c3 - 2)] {
+                assert_eq!(note(name), Some(expected), "{}", name);
+                assert_eq!(full_note_name(expected), name);
+            }
+            assert_eq!(note("bb"), Some(c3 - 2));
+            // The case of the letter says the octave, so "C'" isn't a note.
+            assert_eq!(note("C'"), None);
+            assert_eq!(note("C4"), None);
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use crate::{NOTES_PER_OCTAVE, parse_note};
+
+/// How likely each note is to be picked, for `--weights`. A weight for a
+/// note (`C#4`) beats one for its pitch class (`C#`), which beats the
+/// default.
+pub struct NoteWeights {
+    notes: Vec<(u32, f32)>,
+    pitch_classes: [Option<f32>; NOTES_PER_OCTAVE as usize],
+    default: f32,
+}
+
+impl NoteWeights {
+    /// Reads a weights file: one note or pitch class per line, then its
+    /// weight, like `F#3 2.5`. Blank lines, and anything after a `#` that
+    /// starts a word, are ignored. Unlisted notes get `default`.
+    pub fn read(path: &Path, default: f32) -> Result<NoteWeights, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|x| format!("Couldn't read {:?}: {}", path, x))?;
+        let mut weights = NoteWeights {
+            notes: vec![],
+            pitch_classes: [None; NOTES_PER_OCTAVE as usize],
+            default,
+        };
+        for (index, line) in text.lines().enumerate() {
+            let line = match line.find(" #") {
+                Some(x) => &line[.. x],
+                None if line.trim_start().starts_with('#') => "",
+                None => line,
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() { continue }
+            let bad = || format!("Line {} of {:?} should be a note and a \
+                                  weight, like \"F#3 2.5\".", index + 1,
+                                 path);
+            let [note, weight] = words[..] else { return Err(bad()) };
+            let note = parse_note(note).ok_or_else(bad)?;
+            let weight: f32 = weight.parse().map_err(|_| bad())?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!("Line {} of {:?} has a negative weight.",
+                                   index + 1, path))
+            }
+            match (note.octave, note.note()) {
+                (None, _) => {
+                    weights.pitch_classes[note.pitch_class as usize]
+                        = Some(weight);
+                },
+                (Some(_), Some(note)) => weights.notes.push((note, weight)),
+                (Some(_), None) => return Err(bad()),
+            }
+        }
+        Ok(weights)
+    }
+    /// Weights for `notes`, with `default` for every other note.
+    pub fn new(notes: Vec<(u32, f32)>, default: f32) -> NoteWeights {
+        NoteWeights { notes, pitch_classes: [None; NOTES_PER_OCTAVE as usize],
+                      default }
+    }
+    /// Weights that pick only from `notes`, evenly.
+    pub fn only(notes: &[u32]) -> NoteWeights {
+        NoteWeights {
+            notes: notes.iter().map(|&note| (note, 1.0)).collect(),
+            pitch_classes: [None; NOTES_PER_OCTAVE as usize],
+            default: 0.0,
+        }
+    }
+    pub fn weight(&self, note: u32) -> f32 {
+        match self.notes.iter().rev().find(|&&(x, _)| x == note) {
+            Some(&(_, weight)) => weight,
+            None => self.pitch_classes[(note % NOTES_PER_OCTAVE) as usize]
+                .unwrap_or(self.default),
+        }
+    }
+}
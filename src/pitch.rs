@@ -0,0 +1,126 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Sample rate we record at.
+pub const SAMPLE_RATE: u32 = 44100;
+/// Lowest and highest fundamentals we'll look for.
+const MIN_FREQ: f32 = 50.0;
+const MAX_FREQ: f32 = 2000.0;
+/// How much of the recording, in seconds, to throw away before analyzing it.
+/// People take a moment to settle onto a pitch.
+const SETTLE_TIME: f32 = 0.3;
+const FRAME_SIZE: usize = 2048;
+const FRAME_HOP: usize = 1024;
+/// YIN's threshold for the cumulative mean normalized difference function.
+/// Lower is pickier.
+const YIN_THRESHOLD: f32 = 0.15;
+/// Frames quieter than this (RMS) are considered silence.
+const SILENCE_THRESHOLD: f32 = 0.01;
+
+/// Records `seconds` of mono audio from the default input device, using
+/// SoX's `rec`.
+pub fn record(seconds: f32) -> Vec<f32> {
+    let mut child = Command::new("rec").arg("-q")
+        .arg("-c").arg("1").arg("-r").arg(format!("{}", SAMPLE_RATE))
+        .arg("-b").arg("32").arg("-e").arg("floating-point")
+        .arg("-t").arg("raw").arg("-")
+        .arg("trim").arg("0").arg(format!("{}", seconds))
+        .stdout(Stdio::piped())
+        .spawn().unwrap_or_else(|x| crate::sox_failed("rec", x));
+    let mut bytes = vec![];
+    let _ = child.stdout.take().unwrap().read_to_end(&mut bytes);
+    let _ = child.wait();
+    bytes.chunks_exact(4)
+        .map(|x| f32::from_ne_bytes([x[0], x[1], x[2], x[3]]))
+        .collect()
+}
+
+/// Estimates the fundamental frequency of a recording, in Hz, using the YIN
+/// algorithm on several frames and taking the median. Returns `None` if no
+/// clear pitch was found (silence, noise, etc.).
+pub fn detect_pitch(samples: &[f32]) -> Option<f32> {
+    let skip = ((SETTLE_TIME * SAMPLE_RATE as f32) as usize)
+        .min(samples.len());
+    let samples = &samples[skip..];
+    let max_tau = (SAMPLE_RATE as f32 / MIN_FREQ) as usize;
+    let mut estimates = vec![];
+    let mut start = 0;
+    while start + FRAME_SIZE + max_tau <= samples.len() {
+        if let Some(freq) = yin(&samples[start .. start + FRAME_SIZE + max_tau],
+                                max_tau) {
+            estimates.push(freq);
+        }
+        start += FRAME_HOP;
+    }
+    if estimates.is_empty() { return None }
+    estimates.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    Some(estimates[estimates.len() / 2])
+}
+
+/// Runs YIN on one frame. `frame` must be `FRAME_SIZE + max_tau` samples
+/// long.
+fn yin(frame: &[f32], max_tau: usize) -> Option<f32> {
+    let window = &frame[..FRAME_SIZE];
+    let rms = (window.iter().map(|x| x * x).sum::<f32>()
+               / FRAME_SIZE as f32).sqrt();
+    if rms < SILENCE_THRESHOLD { return None }
+    let min_tau = (SAMPLE_RATE as f32 / MAX_FREQ) as usize;
+    // Cumulative mean normalized difference, indexed by lag.
+    let mut cmnd = vec![1.0f32; max_tau + 1];
+    let mut running_sum = 0.0;
+    for tau in 1 ..= max_tau {
+        let difference: f32 = window.iter().zip(&frame[tau .. tau + FRAME_SIZE])
+            .map(|(a, b)| (a - b) * (a - b)).sum();
+        running_sum += difference;
+        cmnd[tau] = if running_sum > 0.0 {
+            difference * tau as f32 / running_sum
+        } else { 1.0 };
+    }
+    let mut tau = min_tau.max(2);
+    while tau < max_tau {
+        if cmnd[tau] < YIN_THRESHOLD {
+            while tau + 1 < max_tau && cmnd[tau + 1] < cmnd[tau] { tau += 1 }
+            break
+        }
+        tau += 1;
+    }
+    if tau >= max_tau { return None }
+    // Parabolic interpolation around the dip, for sub-sample accuracy.
+    let (prev, here, next) = (cmnd[tau - 1], cmnd[tau], cmnd[tau + 1]);
+    let denominator = prev + next - 2.0 * here;
+    let offset = if denominator.abs() > f32::EPSILON {
+        (prev - next) / (2.0 * denominator)
+    } else { 0.0 };
+    Some(SAMPLE_RATE as f32 / (tau as f32 + offset))
+}
+
+/// How far `freq` is from `target`, in cents. Positive is sharp.
+pub fn cents_off(freq: f32, target: f32) -> f32 {
+    1200.0 * (freq / target).log2()
+}
+
+/// Saves a recording as a 16-bit mono WAV file.
+pub fn write_wav(path: &Path, samples: &[f32]) -> std::io::Result<()> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    std::fs::write(path, bytes)
+}
@@ -0,0 +1,133 @@
+use std::{
+    f32::consts::{PI, TAU},
+    io::Write,
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use clap::ArgEnum;
+
+use crate::{
+    ANTIALIAS_THRESHOLD, MAX_FADE_IN, Tone, Waveform, pitch::SAMPLE_RATE,
+    sox_failed,
+};
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Backend { Native, Sox }
+
+static NATIVE: AtomicBool = AtomicBool::new(true);
+
+pub fn set_backend(backend: Backend) {
+    NATIVE.store(backend == Backend::Native, Ordering::Relaxed);
+}
+
+pub fn native() -> bool { NATIVE.load(Ordering::Relaxed) }
+
+/// How loud a note is, as with SoX.
+const VOLUME: f32 = 0.6;
+
+/// One cycle of `waveform`, at `phase` (0 to 1). With `antialias`, a note
+/// high enough to alias is made from only the harmonics of `freq` below the
+/// Nyquist frequency.
+fn wave(waveform: Waveform, phase: f32, freq: f32, antialias: bool) -> f32 {
+    let antialias = antialias && freq > ANTIALIAS_THRESHOLD;
+    let harmonics = (SAMPLE_RATE as f32 / 2.0 / freq) as u32;
+    match waveform {
+        Waveform::Sine => (phase * TAU).sin(),
+        Waveform::Triangle if !antialias => 1.0 - 4.0 * (phase - 0.5).abs(),
+        Waveform::Square if !antialias => if phase < 0.5 { 1.0 } else { -1.0 },
+        Waveform::Sawtooth if !antialias => 2.0 * phase - 1.0,
+        // Odd harmonics, each 1/n² as loud, alternating in sign.
+        Waveform::Triangle => (1 ..= harmonics).step_by(2).map(|n| {
+            let sign = if n % 4 == 1 { 1.0 } else { -1.0 };
+            sign * (phase * TAU * n as f32).sin() / (n * n) as f32
+        }).sum::<f32>() * 8.0 / (PI * PI),
+        // Odd harmonics, each 1/n as loud.
+        Waveform::Square => (1 ..= harmonics).step_by(2).map(|n| {
+            (phase * TAU * n as f32).sin() / n as f32
+        }).sum::<f32>() * 4.0 / PI,
+        // Every harmonic, each 1/n as loud.
+        Waveform::Sawtooth => -(1 ..= harmonics).map(|n| {
+            (phase * TAU * n as f32).sin() / n as f32
+        }).sum::<f32>() * 2.0 / PI,
+    }
+}
+
+/// Makes tones at `freqs` (which have to be audible) sounding together, as
+/// 16-bit samples at `SAMPLE_RATE`, faded in and out the way `shape_tone`
+/// has SoX do it.
+pub fn synthesize(freqs: &[f32], tone: Tone) -> Vec<i16> {
+    let duration = tone.duration;
+    let fade_in = MAX_FADE_IN.min(duration * 0.25);
+    let fade_out = (duration * 0.7).min(duration - fade_in);
+    let count = (duration * SAMPLE_RATE as f32) as usize;
+    (0 .. count).map(|index| {
+        let time = index as f32 / SAMPLE_RATE as f32;
+        let envelope = (time / fade_in).min((duration - time) / fade_out)
+            .clamp(0.0, 1.0);
+        let mix = freqs.iter().map(|&freq| {
+            wave(tone.waveform, (time * freq).fract(), freq, tone.antialias)
+        }).sum::<f32>() / freqs.len().max(1) as f32;
+        (mix * envelope * VOLUME * i16::MAX as f32) as i16
+    }).collect()
+}
+
+/// Starts a program that plays raw samples from its standard input: ALSA's
+/// `aplay`, or if that isn't there, SoX's `play`. We don't talk to the sound
+/// device ourselves, so one of them has to be installed.
+pub fn start_player() -> Child {
+    let not_found = |x: &std::io::Error| {
+        x.kind() == std::io::ErrorKind::NotFound
+    };
+    let aplay = Command::new("aplay").arg("-q").arg("-t").arg("raw")
+        .arg("-f").arg("S16_LE").arg("-c").arg("1")
+        .arg("-r").arg(format!("{}", SAMPLE_RATE))
+        .stdin(Stdio::piped()).spawn();
+    match aplay {
+        Ok(child) => child,
+        Err(x) if not_found(&x) => {
+            Command::new("play").arg("-q").arg("-t").arg("raw")
+                .arg("-e").arg("signed").arg("-b").arg("16")
+                .arg("-c").arg("1").arg("-r").arg(format!("{}", SAMPLE_RATE))
+                .arg("-")
+                .stdin(Stdio::piped()).spawn()
+                .unwrap_or_else(|x| {
+                    if !not_found(&x) { sox_failed("play", x) }
+                    eprintln!("Couldn't find `aplay` or `play`. Native \
+                               playback still needs one of them to send the \
+                               sound to: is ALSA's aplay (alsa-utils) or SoX \
+                               installed, and in your PATH?");
+                    std::process::exit(1)
+                })
+        },
+        Err(x) => {
+            eprintln!("Couldn't run ALSA's `aplay` to play the sound: {}", x);
+            std::process::exit(1)
+        },
+    }
+}
+
+/// Starts playing `samples` with the player, without waiting for them to
+/// finish.
+fn start_samples(samples: &[i16]) -> Child {
+    let bytes: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes())
+        .collect();
+    let mut child = start_player();
+    let mut stdin = child.stdin.take().unwrap();
+    // The player only takes so much at once, so feed it from elsewhere.
+    std::thread::spawn(move || { let _ = stdin.write_all(&bytes); });
+    child
+}
+
+/// Starts playing tones at `freqs` (which have to be audible) with the
+/// native backend, without waiting for them to finish. Returns `None` if the
+/// SoX backend is in use instead.
+pub fn start(freqs: &[f32], tone: Tone) -> Option<Child> {
+    if !native() { return None }
+    Some(start_samples(&synthesize(freqs, tone)))
+}
+
+/// Like `start`, but plays nothing for `duration` seconds.
+pub fn start_silence(duration: f32) -> Option<Child> {
+    if !native() { return None }
+    Some(start_samples(&vec![0; (duration * SAMPLE_RATE as f32) as usize]))
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::PathBuf,
+    process::{Child, Command},
+};
+use once_cell::sync::OnceCell;
+
+use crate::{Tone, note_freq, shape_tone, sox_failed};
+
+/// Every note in a range, rendered ahead of time into one sound file, one
+/// after another, for `--prerender`. Playing a note is then just playing a
+/// slice of the file, with no synthesis.
+struct Prerendered {
+    path: PathBuf,
+    min_note: u32,
+    max_note: u32,
+    tone: Tone,
+}
+
+static PRERENDERED: OnceCell<Prerendered> = OnceCell::new();
+
+/// Removes the rendered file when dropped.
+pub struct Cleanup;
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if let Some(prerendered) = PRERENDERED.get() {
+            let _ = std::fs::remove_file(&prerendered.path);
+        }
+    }
+}
+
+/// Renders every note from `min_note` to `max_note` with `tone`, in one run
+/// of `sox`. From then on, `start` plays them from the file.
+pub fn render(min_note: u32, max_note: u32, tone: Tone) -> Cleanup {
+    let path = std::env::temp_dir()
+        .join(format!("what-note-{}.wav", std::process::id()));
+    let mut command = Command::new("sox");
+    command.arg("-q").arg("-n").arg(&path);
+    for note in min_note ..= max_note {
+        // Each `:` starts another effects chain, whose output goes on the
+        // end of the file.
+        if note > min_note { command.arg(":"); }
+        match tone.audible(note_freq(note)) {
+            Some(freq) => {
+                command.arg("synth").arg(format!("{}", tone.duration))
+                    .arg(tone.waveform.sox_name()).arg(format!("{}", freq));
+                shape_tone(&mut command, freq, tone.duration, tone);
+            },
+            None => {
+                command.arg("trim").arg("0")
+                    .arg(format!("{}", tone.duration));
+            },
+        }
+    }
+    let status = command.status().unwrap_or_else(|x| sox_failed("sox", x));
+    if !status.success() {
+        eprintln!("Couldn't render the notes ahead of time ({}).", status);
+        std::process::exit(1)
+    }
+    // Notes are found in the file by where they should start, so if it's
+    // not as long as all of them, they'd come out wrong.
+    let count = max_note - min_note + 1;
+    let expected = count as f32 * tone.duration;
+    let length = File::open(&path).ok()
+        .and_then(|file| wav_seconds(BufReader::new(file)));
+    match length {
+        // A sample or so of rounding per note is fine.
+        Some(length) if (length - expected).abs() <= count as f32 * 0.001 => (),
+        _ => {
+            let _ = std::fs::remove_file(&path);
+            eprintln!("The notes rendered ahead of time came out {}, instead \
+                       of {} seconds long. Try again without --prerender.",
+                      length.map_or("an unknown length".to_string(),
+                                    |x| format!("{} seconds long", x)),
+                      expected);
+            std::process::exit(1)
+        },
+    }
+    let _ = PRERENDERED.set(Prerendered { path, min_note, max_note, tone });
+    Cleanup
+}
+
+/// How long the WAV file read by `reader` is, in seconds, going by its
+/// header. `None` if it isn't a WAV file.
+fn wav_seconds(mut reader: impl Read) -> Option<f32> {
+    let mut header = [0; 12];
+    reader.read_exact(&mut header).ok()?;
+    if &header[0 .. 4] != b"RIFF" || &header[8 .. 12] != b"WAVE" {
+        return None
+    }
+    let mut byte_rate = None;
+    loop {
+        let mut chunk = [0; 8];
+        reader.read_exact(&mut chunk).ok()?;
+        let size = u32::from_le_bytes(chunk[4 .. 8].try_into().unwrap());
+        if &chunk[0 .. 4] == b"data" {
+            return Some(size as f32 / byte_rate.filter(|&x| x > 0)? as f32)
+        }
+        // Chunks are padded to an even length.
+        let mut body = vec![0; (size + size % 2) as usize];
+        reader.read_exact(&mut body).ok()?;
+        if &chunk[0 .. 4] == b"fmt " {
+            byte_rate = Some(u32::from_le_bytes(body.get(8 .. 12)?
+                                                .try_into().unwrap()));
+        }
+    }
+}
+
+/// Starts playing `note` from the rendered file, if it's in there (and was
+/// rendered with this `tone`).
+pub fn start(note: u32, tone: Tone) -> Option<Child> {
+    let prerendered = PRERENDERED.get()
+        .filter(|x| x.tone == tone
+                && (x.min_note ..= x.max_note).contains(&note))?;
+    let offset = (note - prerendered.min_note) as f32 * tone.duration;
+    Some(Command::new("play").arg("-q").arg(&prerendered.path)
+         .arg("trim").arg(format!("{}", offset))
+         .arg(format!("{}", tone.duration))
+         .spawn().unwrap_or_else(|x| sox_failed("play", x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The header of a WAV file with `seconds` of 16-bit mono at 48kHz,
+    /// with a chunk before the format, as some writers put there.
+    fn header(seconds: u32) -> Vec<u8> {
+        let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+        wav.extend(b"LIST\x03\0\0\0abc\0");
+        wav.extend(b"fmt \x10\0\0\0\x01\0\x01\0");
+        wav.extend(48000u32.to_le_bytes());
+        wav.extend(96000u32.to_le_bytes());
+        wav.extend(b"\x02\0\x10\0data");
+        wav.extend((seconds * 96000).to_le_bytes());
+        wav
+    }
+
+    #[test]
+    fn wav_length() {
+        assert_eq!(wav_seconds(&header(0)[..]), Some(0.0));
+        assert_eq!(wav_seconds(&header(13)[..]), Some(13.0));
+        assert_eq!(wav_seconds(&b"RIFF\0\0\0\0AVI "[..]), None);
+        assert_eq!(wav_seconds(&header(1)[.. 20]), None);
+    }
+}
@@ -0,0 +1,83 @@
+use clap::{ArgEnum, ArgMatches, ValueSource};
+
+use crate::{Invocation, Waveform};
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PresetName { Beginner, Intermediate, Advanced, AbsolutePitch }
+
+/// A bundle of settings to start from, for `--preset`.
+pub struct Preset {
+    pub name: PresetName,
+    pub description: &'static str,
+    octaves: u32,
+    /// `--from-scale`, or all twelve notes.
+    scale: Option<&'static str>,
+    attempt_limit: u32,
+    waveform: Waveform,
+    duration: f32,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset { name: PresetName::Beginner,
+             description: "the white keys in one octave, with a soft tone",
+             octaves: 1, scale: Some("C major"), attempt_limit: 3,
+             waveform: Waveform::Triangle, duration: 1.5 },
+    Preset { name: PresetName::Intermediate,
+             description: "every note in two octaves",
+             octaves: 2, scale: None, attempt_limit: 2,
+             waveform: Waveform::Triangle, duration: 1.0 },
+    Preset { name: PresetName::Advanced,
+             description: "every note in three octaves, with one guess",
+             octaves: 3, scale: None, attempt_limit: 1,
+             waveform: Waveform::Sawtooth, duration: 0.75 },
+    Preset { name: PresetName::AbsolutePitch,
+             description: "five octaves of short, pure tones, with one guess",
+             octaves: 5, scale: None, attempt_limit: 1,
+             waveform: Waveform::Sine, duration: 0.5 },
+];
+
+impl Preset {
+    pub fn get(name: PresetName) -> &'static Preset {
+        PRESETS.iter().find(|x| x.name == name).unwrap()
+    }
+    /// What this sets, as (argument ID, value).
+    pub fn values(&self) -> Vec<(&'static str, String)> {
+        let waveform = self.waveform.to_possible_value().unwrap();
+        let mut values = vec![
+            ("octaves", self.octaves.to_string()),
+            ("attempt-limit", self.attempt_limit.to_string()),
+            ("waveform", waveform.get_name().to_string()),
+            ("duration", self.duration.to_string()),
+        ];
+        if let Some(scale) = self.scale {
+            values.push(("from-scale", scale.to_string()));
+        }
+        values
+    }
+    /// Whether `id` is left for this preset to set: it wasn't given on the
+    /// command line or in the environment.
+    pub fn sets(&self, matches: &ArgMatches, id: &str) -> bool {
+        let given = matches!(matches.value_source(id),
+                             Some(ValueSource::CommandLine
+                                  | ValueSource::EnvVariable));
+        !given && self.values().iter().any(|&(x, _)| x == id)
+    }
+    /// Fills in everything that wasn't set some other way.
+    pub fn apply(&self, invocation: &mut Invocation, matches: &ArgMatches) {
+        if self.sets(matches, "octaves") { invocation.octaves = self.octaves }
+        if self.sets(matches, "attempt-limit") {
+            invocation.attempt_limit = self.attempt_limit;
+        }
+        if self.sets(matches, "waveform") {
+            invocation.waveform = self.waveform;
+        }
+        if self.sets(matches, "duration") {
+            invocation.duration = self.duration;
+        }
+        // A chord of your own replaces the preset's scale.
+        if self.sets(matches, "from-scale")
+            && invocation.from_chord.is_none() {
+            invocation.from_scale = self.scale.map(str::to_string);
+        }
+    }
+}
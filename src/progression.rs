@@ -0,0 +1,83 @@
+use crate::{NOTES_PER_OCTAVE, glyphs::glyphs, spelling::Spelling};
+
+/// Common progressions, each chord given by the scale degree (counting from
+/// 0) it's built on.
+pub const PROGRESSIONS: &[&[usize]] = &[
+    &[0, 3, 4, 0], // I IV V I
+    &[0, 5, 3, 4], // I vi IV V
+    &[0, 4, 5, 3], // I V vi IV
+    &[5, 3, 0, 4], // vi IV I V
+    &[0, 5, 1, 4], // I vi ii V
+    &[1, 4, 0],    // ii V I
+    &[0, 3, 0, 4], // I IV I V
+    &[0, 1, 4, 0], // I ii V I
+];
+
+/// The tonic of each major key we pick from, if no scale is given, as
+/// (letter, accidental).
+pub const MAJOR_KEYS: &[(usize, i32)] = &[(0,0),(4,0),(1,0),(5,0),(2,0),
+                                          (6,0),(3,1),(1,-1),(5,-1),(2,-1),
+                                          (6,-1),(3,0)];
+
+const NUMERALS: &[&str] = &["I", "II", "III", "IV", "V", "VI", "VII"];
+
+/// The triad built on `degree` of `scale`: every other note, starting there.
+pub fn triad(scale: &[Spelling], degree: usize) -> [Spelling; 3] {
+    [0, 2, 4].map(|x| scale[(degree + x) % scale.len()])
+}
+
+/// Semitones from the root of a triad up to its third and its fifth.
+fn shape(triad: &[Spelling; 3]) -> (u32, u32) {
+    let above_root = |x: Spelling| (x.pitch_class() + NOTES_PER_OCTAVE
+                                    - triad[0].pitch_class())
+        % NOTES_PER_OCTAVE;
+    (above_root(triad[1]), above_root(triad[2]))
+}
+
+/// The notes of a triad in root position, from the first `root` at or above
+/// `tonic`.
+pub fn triad_notes(tonic: u32, triad: &[Spelling; 3]) -> Vec<u32> {
+    let root = tonic + (triad[0].pitch_class() + NOTES_PER_OCTAVE
+                        - tonic % NOTES_PER_OCTAVE) % NOTES_PER_OCTAVE;
+    let (third, fifth) = shape(triad);
+    vec![root, root + third, root + fifth]
+}
+
+/// The roman numeral of the triad on `degree` of `scale`: upper case if it's
+/// major (or augmented, with a `+`), lower case if it's minor (or
+/// diminished, with a `°`).
+pub fn numeral(scale: &[Spelling], degree: usize) -> String {
+    let numeral = NUMERALS[degree];
+    match shape(&triad(scale, degree)) {
+        (4, 8) => format!("{}+", numeral),
+        (4, _) => numeral.to_string(),
+        (_, 6) => format!("{}{}", numeral.to_lowercase(), glyphs().diminished),
+        _ => numeral.to_lowercase(),
+    }
+}
+
+/// The chord symbol of a triad, like `F#m`.
+pub fn symbol(triad: &[Spelling; 3]) -> String {
+    let suffix = match shape(triad) {
+        (4, 8) => "aug",
+        (4, _) => "",
+        (_, 6) => "dim",
+        _ => "m",
+    };
+    format!("{}{}", triad[0].name(), suffix)
+}
+
+/// Parses a progression of roman numerals, like `I vi IV V` or `ii-V-I`,
+/// into scale degrees. Case and quality marks are ignored; only the degree
+/// counts.
+pub fn parse_progression(guess: &str) -> Option<Vec<usize>> {
+    let degrees: Vec<usize> = guess
+        .split(|x: char| x.is_whitespace() || matches!(x, '-' | '–' | ','))
+        .filter(|x| !x.is_empty())
+        .map(|numeral| {
+            let numeral = numeral.trim_end_matches(['o', '°', '+'])
+                .to_ascii_uppercase();
+            NUMERALS.iter().position(|&x| x == numeral)
+        }).collect::<Option<_>>()?;
+    if degrees.is_empty() { None } else { Some(degrees) }
+}
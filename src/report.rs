@@ -0,0 +1,69 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{NOTES_PER_OCTAVE, naming::note_names};
+
+/// How one pitch class fared over a session, for `--breakdown`.
+#[derive(Clone,Default,Serialize,Deserialize)]
+pub struct PitchClassStats {
+    pub rounds: u32,
+    pub perfect: u32,
+    /// Total time between hearing the note and making the first guess.
+    pub response_time: Duration,
+}
+
+pub fn print_breakdown(breakdown: &[PitchClassStats]) {
+    let mut rows: Vec<(usize, &PitchClassStats)> = breakdown.iter()
+        .enumerate().filter(|(_, stats)| stats.rounds > 0).collect();
+    rows.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.response_time
+                                                    / stats.rounds));
+    say!(Essential, "Breakdown by pitch class, slowest first:");
+    say!(Essential, "Note Rounds Correct Avg. time");
+    for (pitch_class, stats) in rows {
+        say!(Essential, "{:>4} {:>6} {:>7} {:>8.2}s",
+             note_names()[pitch_class], stats.rounds, stats.perfect,
+             (stats.response_time / stats.rounds).as_secs_f32());
+    }
+}
+
+/// How many times each pitch class (the row) was guessed as each pitch class
+/// (the column), for `--confusion`.
+pub type ConfusionMatrix = [[u32; NOTES_PER_OCTAVE as usize];
+                            NOTES_PER_OCTAVE as usize];
+
+pub fn print_confusion(out: &mut dyn Write, matrix: &ConfusionMatrix)
+                       -> std::io::Result<()> {
+    writeln!(out, "Confusion matrix (rows are the notes played, columns are \
+                   what you guessed):")?;
+    write!(out, "    ")?;
+    for name in note_names() { write!(out, "{:>4}", name)? }
+    writeln!(out)?;
+    for (played, row) in matrix.iter().enumerate() {
+        if row.iter().all(|&x| x == 0) { continue }
+        write!(out, "{:>4}", note_names()[played])?;
+        for &count in row {
+            if count == 0 { write!(out, "   .")? }
+            else { write!(out, "{:>4}", count)? }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes the confusion matrix as CSV, with a header row and column.
+pub fn write_confusion(path: &Path, matrix: &ConfusionMatrix)
+                       -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "played,{}", note_names().join(","))?;
+    for (played, row) in matrix.iter().enumerate() {
+        let row: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+        writeln!(file, "{},{}", note_names()[played], row.join(","))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::{
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    history::{Outcome, RoundRecord},
+    note_weights::NoteWeights,
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// The ease every note starts with, and the lowest it can go, as in SM-2.
+const INITIAL_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+/// How likely a note is to be picked, compared to one that's never been
+/// reviewed, if it's due (or overdue), or if it isn't due yet.
+const DUE_WEIGHT: f32 = 4.0;
+const NOT_DUE_WEIGHT: f32 = 0.25;
+
+/// What's known about one note, for `--spaced-repetition`.
+#[derive(Clone,Copy,Debug,Serialize,Deserialize)]
+struct Card {
+    note: u32,
+    ease: f32,
+    /// Days between the last review and the next.
+    interval: f32,
+    /// How many times in a row it was recalled.
+    repetitions: u32,
+    /// Seconds since the Unix epoch, when it's next due.
+    due: u64,
+}
+
+impl Card {
+    fn new(note: u32) -> Card {
+        Card { note, ease: INITIAL_EASE, interval: 0.0, repetitions: 0,
+               due: 0 }
+    }
+    /// Updates the card for a review at `now` that went `quality` (0 to 5),
+    /// by SM-2. Anything under 3 starts the note over.
+    fn review(&mut self, quality: u32, now: u64) {
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1.0,
+                1 => 6.0,
+                _ => self.interval * self.ease,
+            };
+            self.repetitions += 1;
+        }
+        else {
+            self.repetitions = 0;
+            self.interval = 1.0;
+        }
+        let miss = (5 - quality) as f32;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02))
+            .max(MIN_EASE);
+        self.due = now + (self.interval * SECONDS_PER_DAY as f32) as u64;
+    }
+}
+
+/// How well a round went, on SM-2's scale of 0 to 5.
+fn quality(outcome: Outcome) -> u32 {
+    match outcome {
+        Outcome::Perfect => 5,
+        Outcome::Hedged => 4,
+        Outcome::WrongOctave => 3,
+        Outcome::Missed => 1,
+    }
+}
+
+/// Every note that's been reviewed, and when it's due again.
+#[derive(Debug,Default,Serialize,Deserialize)]
+pub struct Schedule {
+    cards: Vec<Card>,
+}
+
+/// Where the schedule lives if `--schedule` isn't given:
+/// `$XDG_DATA_HOME/what-note/schedule.json`, next to the history file.
+pub fn default_path() -> Option<PathBuf> {
+    Some(crate::history::default_path()?.with_file_name("schedule.json"))
+}
+
+impl Schedule {
+    /// Reads the schedule at `path`. A missing file is an empty schedule.
+    pub fn load(path: &Path) -> Result<Schedule, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|x| format!("Couldn't read {:?}: {}", path, x)),
+            Err(x) if x.kind() == std::io::ErrorKind::NotFound
+                => Ok(Schedule::default()),
+            Err(x) => Err(format!("Couldn't read {:?}: {}", path, x)),
+        }
+    }
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() { create_dir_all(parent)? }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+    /// Weights that favor notes that are due at `now`, and put off notes
+    /// that aren't. Notes that have never been reviewed get the usual
+    /// weight of 1.
+    pub fn weights(&self, now: u64) -> NoteWeights {
+        NoteWeights::new(self.cards.iter().map(|card| {
+            (card.note, if card.due <= now { DUE_WEIGHT }
+             else { NOT_DUE_WEIGHT })
+        }).collect(), 1.0)
+    }
+    /// How many of the notes from `min_note` to `max_note` are due at `now`,
+    /// counting ones that have never been reviewed.
+    pub fn due_count(&self, min_note: u32, max_note: u32, now: u64) -> usize {
+        (min_note ..= max_note).filter(|&note| {
+            self.cards.iter().find(|card| card.note == note)
+                .is_none_or(|card| card.due <= now)
+        }).count()
+    }
+    /// Reviews each note that came up in `rounds` at `now`, once, by how the
+    /// worst round with that note went.
+    pub fn review(&mut self, rounds: &[RoundRecord], now: u64) {
+        let mut worst: Vec<(u32, u32)> = vec![];
+        for round in rounds {
+            let Some(&note) = round.notes.first() else { continue };
+            let quality = quality(round.outcome);
+            match worst.iter_mut().find(|(x, _)| *x == note) {
+                Some((_, worst)) => *worst = (*worst).min(quality),
+                None => worst.push((note, quality)),
+            }
+        }
+        for (note, quality) in worst {
+            let index = match self.cards.iter()
+                .position(|card| card.note == note) {
+                Some(index) => index,
+                None => {
+                    self.cards.push(Card::new(note));
+                    self.cards.len() - 1
+                },
+            };
+            self.cards[index].review(quality, now);
+        }
+        self.cards.sort_by_key(|card| card.note);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::spelling::Spelling;
+
+/// Each kind of scale we know, and the semitones above the tonic of each of
+/// its seven degrees.
+const SCALE_KINDS: &[(&str, [u32; 7])] = &[
+    ("major", [0, 2, 4, 5, 7, 9, 11]),
+    ("minor", [0, 2, 3, 5, 7, 8, 10]),
+    ("harmonic-minor", [0, 2, 3, 5, 7, 8, 11]),
+    ("melodic-minor", [0, 2, 3, 5, 7, 9, 11]),
+    ("ionian", [0, 2, 4, 5, 7, 9, 11]),
+    ("dorian", [0, 2, 3, 5, 7, 9, 10]),
+    ("phrygian", [0, 1, 3, 5, 7, 8, 10]),
+    ("lydian", [0, 2, 4, 6, 7, 9, 11]),
+    ("mixolydian", [0, 2, 4, 5, 7, 9, 10]),
+    ("aeolian", [0, 2, 3, 5, 7, 8, 10]),
+    ("locrian", [0, 1, 3, 5, 6, 8, 10]),
+];
+
+/// Parses a scale name, like `G major`, `F# harmonic-minor`, or just `Eb`
+/// (major), and spells out its notes from the tonic up.
+pub fn parse_scale(name: &str) -> Option<Vec<Spelling>> {
+    let (tonic, kind) = Spelling::parse_prefix(name.trim())?;
+    let kind = match kind.trim().to_ascii_lowercase().replace(' ', "-") {
+        x if x.is_empty() => "major".to_string(),
+        x => x,
+    };
+    build_scale(tonic, &kind)
+}
+
+/// Spells out the notes of the `kind` scale (like `dorian`) on `tonic`.
+pub fn build_scale(tonic: Spelling, kind: &str) -> Option<Vec<Spelling>> {
+    let (_, degrees) = SCALE_KINDS.iter().find(|(name, _)| *name == kind)?;
+    Some(degrees.iter().enumerate()
+         .map(|(letters, &semitones)| tonic.above(letters, semitones))
+         .collect())
+}
+
+pub fn scale_kind_names() -> Vec<&'static str> {
+    SCALE_KINDS.iter().map(|(name, _)| *name).collect()
+}
@@ -0,0 +1,81 @@
+use crate::{NOTES_PER_OCTAVE, OCTAVE_OFFSET, naming};
+
+/// The pitch class of each letter, with no accidental.
+const LETTER_PITCH_CLASSES: &[i32] = &[0, 2, 4, 5, 7, 9, 11];
+
+/// How a note is written: a letter and some number of sharps (positive) or
+/// flats (negative). `E#` and `F` are different spellings of the same pitch
+/// class.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Spelling {
+    /// Index into `C D E F G A B`.
+    pub letter: usize,
+    pub accidental: i32,
+}
+
+impl Spelling {
+    /// How `pitch_class` is spelled with a sharp, if it needs one.
+    pub fn sharp(pitch_class: u32) -> Spelling {
+        const SHARPS: &[(usize, i32)] = &[(0,0),(0,1),(1,0),(1,1),(2,0),
+                                          (3,0),(3,1),(4,0),(4,1),(5,0),
+                                          (5,1),(6,0)];
+        let (letter, accidental) = SHARPS[pitch_class as usize];
+        Spelling { letter, accidental }
+    }
+    /// Parses a spelling off the front of `name`, like the `Bb` of `Bbm7`.
+    /// Returns it and whatever's left. `x` is a double sharp, and the Unicode
+    /// accidentals work too.
+    pub fn parse_prefix(name: &str) -> Option<(Spelling, &str)> {
+        let (letter, mut accidental, mut rest) = naming::parse_letter(name)?;
+        loop {
+            let mut chars = rest.chars();
+            accidental += match chars.next() {
+                Some('#' | '♯') => 1,
+                Some('b' | '♭') => -1,
+                Some('x' | '𝄪') => 2,
+                Some('𝄫') => -2,
+                _ => break,
+            };
+            rest = chars.as_str();
+        }
+        Some((Spelling { letter, accidental }, rest))
+    }
+    /// Semitones above the C in the letter's octave. This is negative for
+    /// `Cb`, and 12 or more for `B#`.
+    pub fn semitones_above_c(self) -> i32 {
+        LETTER_PITCH_CLASSES[self.letter] + self.accidental
+    }
+    /// The pitch class, semitones above C within an octave.
+    pub fn pitch_class(self) -> u32 {
+        self.semitones_above_c().rem_euclid(NOTES_PER_OCTAVE as i32) as u32
+    }
+    /// The spelling `letters` letter names and `semitones` semitones above
+    /// this one. (A major third above `D` is two letters and four
+    /// semitones: `F#`.)
+    pub fn above(self, letters: usize, semitones: u32) -> Spelling {
+        let letter = (self.letter + letters) % LETTER_PITCH_CLASSES.len();
+        let natural = LETTER_PITCH_CLASSES[letter];
+        let target = (self.pitch_class() + semitones) as i32;
+        // the nearest way to get from the natural letter to the target
+        let mut accidental = (target - natural)
+            .rem_euclid(NOTES_PER_OCTAVE as i32);
+        if accidental > NOTES_PER_OCTAVE as i32 / 2 {
+            accidental -= NOTES_PER_OCTAVE as i32;
+        }
+        Spelling { letter, accidental }
+    }
+    pub fn name(self) -> String {
+        naming::spell(self.letter, self.accidental)
+    }
+    /// The name of `note` spelled this way, with its octave. The octave goes
+    /// with the letter, so `B#2` is the same note as `C3`.
+    pub fn full_name(self, note: u32) -> String {
+        naming::with_octave(&self.name(), self.octave(note))
+    }
+    /// The octave number `note` has when it's spelled this way, numbered the
+    /// `--octave-convention c3` way.
+    pub fn octave(self, note: u32) -> i32 {
+        let natural = note as i32 - self.accidental;
+        natural.div_euclid(NOTES_PER_OCTAVE as i32) - OCTAVE_OFFSET as i32
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{MIDDLE_C, NOTES_PER_OCTAVE, glyphs::glyphs, spelling::Spelling};
+
+/// Letters in an octave, which is how many staff positions it spans.
+const LETTERS: i32 = 7;
+/// The staff position (counting letters up from C0) of the bottom line of the
+/// treble clef, E3, and of the bass clef, G1.
+const TREBLE_BOTTOM: i32 = 3 * LETTERS + 2;
+const BASS_BOTTOM: i32 = LETTERS + 4;
+/// Positions from the bottom line of a staff to the top.
+const STAFF_SPAN: i32 = 8;
+const STAFF_WIDTH: usize = 17;
+const HEAD_COLUMN: usize = STAFF_WIDTH / 2;
+/// How far a ledger line sticks out on either side of the note.
+const LEDGER_REACH: usize = 2;
+
+/// Draws `note`, written with `spelling`, on a treble staff (or a bass staff,
+/// if it's below middle C), with ledger lines as needed. With `marked`, the
+/// note head stands out, for pointing out the answer.
+pub fn render_staff(note: u32, spelling: Spelling, marked: bool) -> String {
+    let glyphs = glyphs();
+    let position = spelling.octave(note) * LETTERS + spelling.letter as i32;
+    let (clef, bottom) = if note >= MIDDLE_C { ("treble", TREBLE_BOTTOM) }
+    else { ("bass", BASS_BOTTOM) };
+    let top = bottom + STAFF_SPAN;
+    let mut rows = vec![format!("({} clef)", clef)];
+    for row in (position.min(bottom - 1) ..= position.max(top + 1)).rev() {
+        let on_line = (row - bottom) % 2 == 0;
+        let ledger = on_line && ((row < bottom && row >= position)
+                                 || (row > top && row <= position));
+        let mut line: Vec<char> = (0 .. STAFF_WIDTH).map(|column| {
+            let staff = on_line && (bottom ..= top).contains(&row);
+            if staff || (ledger && column.abs_diff(HEAD_COLUMN)
+                         <= LEDGER_REACH) {
+                glyphs.staff_line
+            }
+            else { ' ' }
+        }).collect();
+        if row == position {
+            line[HEAD_COLUMN] = if marked { glyphs.marked_note }
+            else { glyphs.note_head };
+            let accidental = if spelling.accidental < 0 { 'b' } else { '#' };
+            for n in 0 .. spelling.accidental.unsigned_abs() as usize {
+                line[HEAD_COLUMN - 1 - n] = accidental;
+            }
+        }
+        rows.push(line.into_iter().collect::<String>().trim_end().to_string());
+    }
+    rows.join("\n")
+}
+
+/// The spelling a note gets on the staff if nothing else says how it's
+/// spelled: with a sharp, if it needs one.
+pub fn default_spelling(note: u32) -> Spelling {
+    Spelling::sharp(note % NOTES_PER_OCTAVE)
+}
@@ -0,0 +1,98 @@
+use std::{
+    io::{Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::Mutex,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use once_cell::sync::OnceCell;
+
+use crate::{Tone, pitch::SAMPLE_RATE, playback, sox_failed, synth_command};
+
+/// One player that stays open for the whole session, for `--low-latency`:
+/// the native backend's (see `playback::start_player`), or SoX's `play`.
+/// Notes are made (by `--playback`'s backend) and fed into it, so the sound
+/// device is only opened once, instead of once per note.
+struct Stream {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// When everything written so far will have finished playing.
+    end: Instant,
+}
+
+static STREAM: OnceCell<Mutex<Stream>> = OnceCell::new();
+
+/// The raw format that goes down the stream: 16-bit signed mono.
+fn raw_format(command: &mut Command) {
+    command.arg("-t").arg("raw").arg("-r").arg(format!("{}", SAMPLE_RATE))
+        .arg("-e").arg("signed").arg("-b").arg("16").arg("-c").arg("1");
+}
+
+/// Closes the stream when dropped, and waits for the player to finish what
+/// it was given.
+pub struct Close;
+
+impl Drop for Close {
+    fn drop(&mut self) {
+        if let Some(stream) = STREAM.get() {
+            let mut stream = stream.lock().unwrap();
+            stream.stdin = None;
+            let _ = stream.child.wait();
+        }
+    }
+}
+
+/// Starts the player that notes will be fed into. From then on, `play`
+/// sends notes there.
+pub fn open() -> Close {
+    let mut child = if playback::native() { playback::start_player() }
+    else {
+        let mut command = Command::new("play");
+        command.arg("-q");
+        raw_format(&mut command);
+        command.arg("-").stdin(Stdio::piped()).spawn()
+            .unwrap_or_else(|x| sox_failed("play", x))
+    };
+    let stdin = child.stdin.take();
+    let _ = STREAM.set(Mutex::new(Stream { child, stdin,
+                                           end: Instant::now() }));
+    Close
+}
+
+/// Plays tones at `freqs` at once through the stream, and waits for them to
+/// finish. Returns `false` (having done nothing) if there's no stream.
+pub fn play(freqs: &[f32], tone: Tone) -> bool {
+    let Some(stream) = STREAM.get() else { return false };
+    let mut stream = stream.lock().unwrap();
+    let audible: Vec<f32> = freqs.iter().filter_map(|&x| tone.audible(x))
+        .collect();
+    let samples = match synth_command(Some(raw_format), freqs, tone) {
+        Some(_) if playback::native() => {
+            playback::synthesize(&audible, tone).iter()
+                .flat_map(|x| x.to_le_bytes()).collect()
+        },
+        Some(mut command) => {
+            let mut child = command.stdout(Stdio::piped()).spawn()
+                .unwrap_or_else(|x| sox_failed("sox", x));
+            let mut samples = vec![];
+            let _ = child.stdout.take().unwrap().read_to_end(&mut samples);
+            let _ = child.wait();
+            samples
+        },
+        None => vec![0; (tone.duration * SAMPLE_RATE as f32) as usize * 2],
+    };
+    let Some(stdin) = stream.stdin.as_mut() else { return false };
+    if let Err(x) = stdin.write_all(&samples) {
+        eprintln!("Couldn't send a note to the player: {}", x);
+        std::process::exit(1)
+    }
+    // The player may have run dry since the last note, in which case this
+    // one starts now.
+    let length = Duration::from_secs_f32(samples.len() as f32 / 2.0
+                                         / SAMPLE_RATE as f32);
+    stream.end = stream.end.max(Instant::now()) + length;
+    let end = stream.end;
+    drop(stream);
+    sleep(end.saturating_duration_since(Instant::now()));
+    true
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use clap::ArgEnum;
+
+use crate::{BASE_FREQ, NOTES_PER_OCTAVE, note_freq};
+
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Temperament { Equal, Just }
+
+/// Named standards for what frequency A3 is.
+#[derive(ArgEnum,Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PitchStandard {
+    A440,
+    A432,
+    #[clap(name = "baroque-415")]
+    Baroque415,
+    #[clap(name = "classical-430")]
+    Classical430,
+}
+
+impl PitchStandard {
+    pub fn freq(self) -> f32 {
+        match self {
+            PitchStandard::A440 => 440.0,
+            PitchStandard::A432 => 432.0,
+            PitchStandard::Baroque415 => 415.0,
+            PitchStandard::Classical430 => 430.0,
+        }
+    }
+}
+
+/// The frequency of A3, as the bits of an `f32`.
+static BASE_FREQ_BITS: AtomicU32 = AtomicU32::new(BASE_FREQ.to_bits());
+
+pub fn set_base_freq(freq: f32) {
+    BASE_FREQ_BITS.store(freq.to_bits(), Ordering::Relaxed);
+}
+
+/// The frequency of A3 (the A above middle C), which every other note is
+/// tuned from.
+pub fn base_freq() -> f32 {
+    f32::from_bits(BASE_FREQ_BITS.load(Ordering::Relaxed))
+}
+
+/// Just intonation ratios (5-limit) for each number of semitones above the
+/// root, within an octave:
+///
+/// | Semitones | Interval | Ratio |
+/// |-----------|----------|-------|
+/// | 0         | P1       | 1/1   |
+/// | 1         | m2       | 16/15 |
+/// | 2         | M2       | 9/8   |
+/// | 3         | m3       | 6/5   |
+/// | 4         | M3       | 5/4   |
+/// | 5         | P4       | 4/3   |
+/// | 6         | TT       | 45/32 |
+/// | 7         | P5       | 3/2   |
+/// | 8         | m6       | 8/5   |
+/// | 9         | M6       | 5/3   |
+/// | 10        | m7       | 9/5   |
+/// | 11        | M7       | 15/8  |
+const JUST_RATIOS: &[(u32, u32)] = &[(1,1),(16,15),(9,8),(6,5),(5,4),(4,3),
+                                     (45,32),(3,2),(8,5),(5,3),(9,5),(15,8)];
+
+/// With `--temperament just`, the pitch class that single notes are tuned
+/// from, or `NO_TONIC`.
+static TONIC: AtomicU32 = AtomicU32::new(NO_TONIC);
+const NO_TONIC: u32 = u32::MAX;
+
+pub fn set_tonic(pitch_class: u32) {
+    TONIC.store(pitch_class, Ordering::Relaxed);
+}
+
+/// How far `note` is from equal temperament, in semitones, once it's tuned
+/// to a pure ratio above the tonic (see `set_tonic`). Without a tonic, it
+/// isn't.
+pub fn just_offset(note: u32) -> f32 {
+    let tonic = TONIC.load(Ordering::Relaxed);
+    if tonic == NO_TONIC { return 0.0 }
+    let semitones = (note + NOTES_PER_OCTAVE - tonic) % NOTES_PER_OCTAVE;
+    let (num, den) = JUST_RATIOS[semitones as usize];
+    (num as f32 / den as f32).log2() * NOTES_PER_OCTAVE as f32
+        - semitones as f32
+}
+
+/// Works out the frequency of each note. In equal temperament, every note
+/// gets its usual frequency. In just intonation, the root gets its usual
+/// frequency and every other note is tuned to a pure ratio above (or below)
+/// it.
+pub fn freqs(notes: &[u32], root: u32, temperament: Temperament) -> Vec<f32> {
+    match temperament {
+        Temperament::Equal => notes.iter().map(|&note| note_freq(note))
+            .collect(),
+        Temperament::Just => {
+            let root_freq = note_freq(root);
+            notes.iter().map(|&note| {
+                let semitones = note as i32 - root as i32;
+                let octaves = semitones.div_euclid(NOTES_PER_OCTAVE as i32);
+                let (num, den) = JUST_RATIOS[semitones
+                    .rem_euclid(NOTES_PER_OCTAVE as i32) as usize];
+                root_freq * num as f32 / den as f32
+                    * 2.0f32.powi(octaves)
+            }).collect()
+        },
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fmt::Arguments,
+    io::{ErrorKind, Write},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// How chatty a message is. A message is printed if the verbosity is at
+/// least its level.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Level {
+    /// Prompts, answers, and results. Always printed.
+    Essential,
+    /// Round separators, range info, and such. Hidden by `--quiet`.
+    Normal,
+    /// Timing and frequency details. Only printed with `--verbose`.
+    Verbose,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+/// Sets the verbosity from the number of times `-q` and `-v` were given.
+/// They cancel each other out.
+pub fn set_verbosity(quiet: u64, verbose: u64) {
+    let verbosity = (Level::Normal as i64 + verbose as i64 - quiet as i64)
+        .clamp(Level::Essential as i64, Level::Verbose as i64);
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn shows(level: Level) -> bool {
+    level as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Writes a line to `out`. Returns `false` if whoever was reading it has
+/// gone away (as when we're piped into `head`). Any other error is fatal.
+fn write_line(out: &mut dyn Write, args: Arguments) -> bool {
+    match writeln!(out, "{}", args) {
+        Ok(()) => true,
+        Err(x) if x.kind() == ErrorKind::BrokenPipe => false,
+        Err(x) => {
+            eprintln!("Couldn't print: {}", x);
+            std::process::exit(1)
+        },
+    }
+}
+
+/// Prints a line, for `say!`. If nothing's reading it any more, there's no
+/// one to talk to, so this quietly exits instead of panicking the way
+/// `println!` does.
+pub fn print_line(args: Arguments) {
+    print_line_to(&mut std::io::stdout().lock(), args)
+}
+
+/// Like `print_line`, but to `out`.
+pub fn print_line_to(out: &mut dyn Write, args: Arguments) {
+    if !write_line(out, args) { std::process::exit(0) }
+}
+
+/// Like `println!`, but only if the verbosity is at least the given `Level`.
+macro_rules! say {
+    ($level:ident) => {
+        if $crate::verbosity::shows($crate::verbosity::Level::$level) {
+            $crate::verbosity::print_line(format_args!(""))
+        }
+    };
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::verbosity::shows($crate::verbosity::Level::$level) {
+            $crate::verbosity::print_line(format_args!($($arg)*))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_reader() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        assert!(write_line(&mut writer, format_args!("C4")));
+        drop(reader);
+        assert!(!write_line(&mut writer, format_args!("E4")));
+    }
+}